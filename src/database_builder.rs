@@ -1,22 +1,92 @@
 use crate::{
-    dtype::Dtype,
-    normalizer::{Normifier, TableData},
+    dtype::{ColumnType, Dtype},
+    error::{NormError, Result},
+    keys::candidate_keys,
+    normalizer::{ID_COLUMN, METADATA_COLUMNS, Normifier, ORDINAL_COLUMN, TableData},
+    relations::RelationGraph,
+    schema::{ForeignKey, TableSchema},
 };
 use indexmap::IndexMap;
 use polars::{frame::DataFrame, prelude::*, series::IntoSeries};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Default)]
 pub struct DataBase {
-    pub schemas: IndexMap<String, Schema>,
-    pub tables: Vec<DataFrame>,
+    pub schemas: IndexMap<String, TableSchema>,
+    pub tables: IndexMap<String, DataFrame>,
+    pub relations: RelationGraph,
+    // whether the document was an array of records, `to_json` rebuilds the same shape
+    pub root_is_array: bool,
 }
 
 impl DataBase {
     pub fn new() -> Self {
-        DataBase {
-            schemas: IndexMap::new(),
-            tables: Vec::new(),
+        Self::default()
+    }
+
+    pub fn get(&self, table_name: &str) -> Option<&DataFrame> {
+        self.tables.get(table_name)
+    }
+
+    pub fn get_mut(&mut self, table_name: &str) -> Option<&mut DataFrame> {
+        self.tables.get_mut(table_name)
+    }
+
+    pub fn iter(&self) -> indexmap::map::Iter<'_, String, DataFrame> {
+        // yields (table name, dataframe) pairs in the order the tables were created
+        self.tables.iter()
+    }
+
+    pub fn table_names(&self) -> impl Iterator<Item = &str> {
+        self.tables.keys().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.tables.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty()
+    }
+
+    pub fn insert(&mut self, table_name: &str, df: DataFrame) -> Option<DataFrame> {
+        // adds a table at the end, or replaces an existing table in place
+        self.tables.insert(table_name.to_string(), df)
+    }
+
+    pub fn remove(&mut self, table_name: &str) -> Option<DataFrame> {
+        // shift_remove keeps the creation order of the remaining tables intact
+        self.schemas.shift_remove(table_name);
+        for schema in self.schemas.values_mut() {
+            // the referencing columns keep their values but are no longer foreign keys
+            schema.foreign_keys.retain(|fk| fk.references != table_name);
         }
+        self.relations.remove_table(table_name);
+        self.tables.shift_remove(table_name)
+    }
+
+    pub fn rename(&mut self, table_name: &str, new_name: &str) -> Result<()> {
+        // renames a table without changing its position
+        if self.tables.contains_key(new_name) {
+            return Err(NormError::DuplicateTable(new_name.to_string()));
+        }
+        let (idx, _, df) = self
+            .tables
+            .shift_remove_full(table_name)
+            .ok_or_else(|| NormError::UnknownTable(table_name.to_string()))?;
+        self.tables.shift_insert(idx, new_name.to_string(), df);
+        if let Some((idx, _, schema)) = self.schemas.shift_remove_full(table_name) {
+            self.schemas.shift_insert(idx, new_name.to_string(), schema);
+        }
+        for schema in self.schemas.values_mut() {
+            // keep foreign keys of child tables pointing at the renamed table
+            for fk in schema.foreign_keys.iter_mut() {
+                if fk.references == table_name {
+                    fk.references = new_name.to_string();
+                }
+            }
+        }
+        self.relations.rename_table(table_name, new_name);
+        Ok(())
     }
 
     pub fn stringify_collection(collection: Vec<Dtype>) -> Series {
@@ -29,106 +99,99 @@ impl DataBase {
         }))
     }
 
-    pub fn stringify_nested_collection(name: String, n_collection: Vec<Vec<Dtype>>) -> Series {
+    pub fn stringify_nested_collection(
+        name: String,
+        n_collection: Vec<Option<Vec<Dtype>>>,
+    ) -> Result<Series> {
         // todo: max subarray size\
         let values_cap: usize = n_collection
             .iter()
-            .map(|x| x.len())
+            .map(|x| x.as_ref().map_or(0, Vec::len))
             .max()
             .unwrap_or_default();
         let mut s_builder: ListStringChunkedBuilder =
-            ListStringChunkedBuilder::new(name.into(), n_collection.len(), values_cap);
+            ListStringChunkedBuilder::new(name.clone().into(), n_collection.len(), values_cap);
         for inner_array in n_collection {
-            s_builder
-                .append_series(&(Self::stringify_collection(inner_array)))
-                .expect("failed appending series");
+            match inner_array {
+                Some(inner_array) => s_builder
+                    .append_series(&(Self::stringify_collection(inner_array)))
+                    .map_err(|e| NormError::polars(&name, e))?,
+                None => s_builder.append_null(),
+            }
         }
-        s_builder.finish().into_series()
+        Ok(s_builder.finish().into_series())
     }
-    pub fn is_normal_collection(determinant: &Dtype, collection: &[Dtype]) -> bool {
+    pub fn collection_to_series(target: ColumnType, collection: Vec<Dtype>) -> Series {
+        // casts every value to the column's unified type, promoting narrower numbers
         use Dtype as DT;
-        match determinant {
-            DT::Bool(_) => collection.iter().all(|x| x.is_bool() || x.is_null()),
-            DT::UInt(_) => collection.iter().all(|x| x.is_uint() || x.is_null()),
-            DT::Int(_) => collection.iter().all(|x| x.is_int() || x.is_null()),
-            DT::Float(_) => collection.iter().all(|x| x.is_float() || x.is_null()),
-            DT::String(_) => collection.iter().all(|x| x.is_string() || x.is_null()),
-            _ => panic!("Cannot call is_normal_collection on a nested structure"),
-        }
-    }
-
-    pub fn collection_to_series(determinant: &Dtype, collection: Vec<Dtype>) -> Series {
-        use Dtype as DT;
-        match determinant {
-            DT::Bool(_) => collection
+        match target {
+            ColumnType::Bool => collection
                 .into_iter()
                 .map(|x| if let DT::Bool(b) = x { Some(b) } else { None })
                 .collect(),
-            DT::UInt(_) => collection
+            ColumnType::UInt => collection
                 .into_iter()
                 .map(|x| if let DT::UInt(u) = x { Some(u) } else { None })
                 .collect(),
-            DT::Int(_) => collection
-                .into_iter()
-                .map(|x| if let DT::Int(i) = x { Some(i) } else { None })
-                .collect(),
-            DT::Float(_) => collection
-                .into_iter()
-                .map(|x| if let DT::Float(f) = x { Some(f) } else { None })
-                .collect(),
-            DT::String(_) => collection
+            ColumnType::Int => collection.iter().map(Dtype::as_i64).collect(),
+            ColumnType::Float => collection.iter().map(Dtype::as_f64).collect(),
+            ColumnType::String => collection
                 .into_iter()
                 .map(|x| if let DT::String(s) = x { Some(s) } else { None })
                 .collect(),
-            DT::Array(_) => {
-                panic!("cannot parse a nested collection")
-            }
-            DT::Null => {
-                panic!("collection should not contain all null values")
-            }
         }
     }
 
     fn build_list_chunked(
-        data: Vec<Series>,
+        data: Vec<Option<Series>>,
         builder: &mut Box<dyn ListBuilderTrait>,
-    ) -> ListChunked {
+    ) -> Result<ListChunked> {
         for s in data {
-            builder.append_series(&s).expect("could not build column");
+            match s {
+                Some(s) => builder
+                    .append_series(&s)
+                    .map_err(|e| NormError::polars(s.name(), e))?,
+                None => builder.append_null(),
+            }
         }
-        builder.finish()
+        Ok(builder.finish())
     }
 
-    fn get_list_builder(determinant: &Dtype, data: &[Vec<Dtype>]) -> Box<dyn ListBuilderTrait> {
+    fn get_list_builder(
+        target: ColumnType,
+        data: &[Option<Vec<Dtype>>],
+    ) -> Box<dyn ListBuilderTrait> {
         // returns a new list_builder according to columns capacity and the list's inner type and capacity
         let capacity = data.len();
-        let values_capacity = data.iter().map(|x| x.len()).max().unwrap_or_default();
-        match determinant {
-            &Dtype::Array(_) => panic!("cannot call get_list_builder on array type"),
-            &Dtype::Null => panic!("cannot call get_list_builder on null type"),
-            &Dtype::String(_) => Box::new(ListStringChunkedBuilder::new(
+        let values_capacity = data
+            .iter()
+            .map(|x| x.as_ref().map_or(0, Vec::len))
+            .max()
+            .unwrap_or_default();
+        match target {
+            ColumnType::String => Box::new(ListStringChunkedBuilder::new(
                 PlSmallStr::EMPTY,
                 capacity,
                 values_capacity,
             )),
-            &Dtype::Bool(_) => Box::new(ListBooleanChunkedBuilder::new(
+            ColumnType::Bool => Box::new(ListBooleanChunkedBuilder::new(
                 PlSmallStr::EMPTY,
                 capacity,
                 values_capacity,
             )),
-            &Dtype::UInt(_) => Box::new(ListPrimitiveChunkedBuilder::<UInt64Type>::new(
+            ColumnType::UInt => Box::new(ListPrimitiveChunkedBuilder::<UInt64Type>::new(
                 PlSmallStr::EMPTY,
                 capacity,
                 values_capacity,
                 DataType::UInt64,
             )),
-            &Dtype::Int(_) => Box::new(ListPrimitiveChunkedBuilder::<Int64Type>::new(
+            ColumnType::Int => Box::new(ListPrimitiveChunkedBuilder::<Int64Type>::new(
                 PlSmallStr::EMPTY,
                 capacity,
                 values_capacity,
                 DataType::Int64,
             )),
-            &Dtype::Float(_) => Box::new(ListPrimitiveChunkedBuilder::<Float64Type>::new(
+            ColumnType::Float => Box::new(ListPrimitiveChunkedBuilder::<Float64Type>::new(
                 PlSmallStr::EMPTY,
                 capacity,
                 values_capacity,
@@ -140,96 +203,196 @@ impl DataBase {
         custom implemention to allow easy unwrapping, processing, and conversion
     */
 
-    // unwrap a vector of Dtype::Array variants into vectors
-    fn unwrap_nested(nested: Vec<Dtype>) -> Vec<Vec<Dtype>> {
+    // unwrap a vector of Dtype::Array variants into vectors, null cells stay null lists
+    fn unwrap_nested(nested: Vec<Dtype>) -> Result<Vec<Option<Vec<Dtype>>>> {
         if !Dtype::array_is_type(&nested, Dtype::is_array) {
             // checks that every element is either an array variant or null variant
-            panic!("cannot pass flat vector to unwrap_nested")
+            return Err(NormError::build(
+                "",
+                "cannot pass flat vector to unwrap_nested",
+            ));
         }
         let mut unnested = vec![];
         for sub_array in nested {
             if let Dtype::Array(a) = sub_array {
-                unnested.push(a);
+                unnested.push(Some(a));
             } else {
-                unnested.push(vec![Dtype::Null]);
+                unnested.push(None);
             }
         }
-        unnested
-    }
-    pub fn build_series(name: String, data: Vec<Dtype>) -> Series {
-        // todo: handle case where some elements are Uints and other are Ints
-        // let the first non-null value in the vector determine the target type for the series
-        println!("parsing column {}", name);
-        let determining_element: Dtype = data.iter().find(|&x| !x.is_null()).unwrap().clone();
-        let normal: bool;
-        if matches!(determining_element, Dtype::Array(_)) {
-            // if data is an vector of array types, find the first non-null element within the flattened data
-            let unnested_data: Vec<Vec<Dtype>> = Self::unwrap_nested(data);
-            let array_determinant: Dtype = unnested_data
-                .iter()
-                .flatten()
-                .find(|&x| !x.is_null())
-                .unwrap()
-                .clone();
-
-            normal = unnested_data
-                .iter()
-                .all(|x| Self::is_normal_collection(&array_determinant, x));
-
-            if normal {
-                println!("column: {} is nested and already normal", name);
-                let mut list_builder = Self::get_list_builder(&array_determinant, &unnested_data);
-                let mut series_vec: Vec<Series> = vec![];
-                for sub_array in unnested_data.into_iter() {
-                    // cast each subarray to a series
-                    series_vec.push(Self::collection_to_series(&array_determinant, sub_array));
+        Ok(unnested)
+    }
+    pub fn build_series(name: String, data: Vec<Dtype>) -> Result<Series> {
+        Self::build_column(name, data).map(|(series, _)| series)
+    }
+
+    fn build_column(name: String, data: Vec<Dtype>) -> Result<(Series, bool)> {
+        // builds a series and reports whether its values had to be coerced to strings
+        if data.iter().all(Dtype::is_null) {
+            // a column without any values has no type to infer
+            return Ok((
+                Series::full_null(name.into(), data.len(), &DataType::Null),
+                false,
+            ));
+        }
+        if Dtype::array_is_type(&data, Dtype::is_array) {
+            // if data is a vector of array types, unify the types of the flattened inner values
+            let unnested_data: Vec<Option<Vec<Dtype>>> =
+                Self::unwrap_nested(data).map_err(|e| e.in_column(&name))?;
+            if unnested_data.iter().flatten().flatten().all(Dtype::is_null) {
+                // every sub-array is empty or null, keep the column as a list of null strings
+                return Ok((
+                    Self::stringify_nested_collection(name, unnested_data)?,
+                    false,
+                ));
+            }
+            match Dtype::unify(unnested_data.iter().flatten().flatten()) {
+                Some(inner_type) => {
+                    let mut list_builder = Self::get_list_builder(inner_type, &unnested_data);
+                    let series_vec: Vec<Option<Series>> = unnested_data
+                        .into_iter()
+                        // cast each subarray to a series
+                        .map(|sub_array| {
+                            sub_array.map(|a| Self::collection_to_series(inner_type, a))
+                        })
+                        .collect();
+                    let list: ListChunked = Self::build_list_chunked(series_vec, &mut list_builder)
+                        .map_err(|e| e.in_column(&name))?;
+                    Ok((list.into_series(), false))
+                }
+                None => {
+                    // if nested array column has no common inner type,
+                    // convert each element to a string
+                    Ok((
+                        Self::stringify_nested_collection(name, unnested_data)?,
+                        true,
+                    ))
                 }
-                Self::build_list_chunked(series_vec, &mut list_builder).into_series()
-            } else {
-                println!("column: {} is nested and is not normal", name);
-                // if nested array column is not normal,
-                // convert each element to a string
-                Self::stringify_nested_collection(name, unnested_data)
             }
         } else {
-            println!("column: {} is flat and already normal", name);
-            // if the target type is a Dtype-primitive, check that all elements are of that type as well
-            normal = Self::is_normal_collection(&determining_element, data.as_slice());
-            if normal {
-                // if data is already normal, cast to a series
-                Self::collection_to_series(&determining_element, data)
-            } else {
-                println!("column: {} is nested and not normal", name);
-                // if data is not normal, represent all data as strings and cast to a series
-                Self::stringify_collection(data)
+            match Dtype::unify(&data) {
+                Some(column_type) => {
+                    // if data has a common type, cast to a series
+                    Ok((Self::collection_to_series(column_type, data), false))
+                }
+                None => {
+                    // if data has no common type, represent all data as strings and cast to a series
+                    Ok((Self::stringify_collection(data), true))
+                }
             }
         }
     }
 
-    pub fn build_df(&mut self, name: String, data: TableData) {
-        // builds a dataframe from a TableData struct
-        println!("creating df {}", name);
+    pub fn build_df(&mut self, name: String, data: TableData) -> Result<()> {
+        // builds a dataframe from a TableData struct and records its schema
         let mut df_data: Vec<Column> = vec![];
+        let mut stringified: Vec<String> = vec![];
+        let mut nullable: Vec<String> = vec![];
         for (field, data) in data.columns.into_iter() {
-            let s: Series = DataBase::build_series(field.clone(), data);
+            let (s, coerced): (Series, bool) =
+                DataBase::build_column(field.clone(), data).map_err(|e| e.in_table(&name))?;
+            if coerced {
+                stringified.push(field.clone());
+            }
+            if s.null_count() > 0 {
+                nullable.push(field.clone());
+            }
             let mut c: Column = s.into_column();
             c.rename(field.into());
             df_data.push(c);
         }
-        let df_result = DataFrame::new(df_data);
-        if let Ok(df) = df_result {
-            self.tables.push(df);
-        } else {
-            println!("{:?}", df_result);
+        let df: DataFrame =
+            DataFrame::new(df_data).map_err(|e| NormError::polars("", e).in_table(&name))?;
+
+        let mut table_schema: TableSchema = TableSchema::new(df.schema().as_ref().clone());
+        if table_schema.dtype(&data.key_column).is_some() {
+            table_schema.primary_key = Some(data.key_column.clone());
         }
+        table_schema.foreign_keys = data
+            .foreign_keys
+            .into_iter()
+            .filter(|(column, _)| table_schema.dtype(column).is_some())
+            .map(|(column, references)| ForeignKey {
+                column,
+                references,
+                // replaced by the referenced table's key column once every table is built
+                referenced_column: ID_COLUMN.to_string(),
+            })
+            .collect();
+        table_schema.stringified = stringified;
+        table_schema.nullable = nullable;
+        table_schema.metadata = data
+            .generated
+            .iter()
+            .filter(|(column, _)| METADATA_COLUMNS.contains(&column.as_str()))
+            .map(|(_, renamed)| renamed.clone())
+            .filter(|renamed| table_schema.dtype(renamed).is_some())
+            .collect();
+        table_schema.ordinal = data
+            .generated
+            .get(ORDINAL_COLUMN)
+            .filter(|ordinal| table_schema.dtype(ordinal).is_some())
+            .cloned();
+        table_schema.candidate_keys = candidate_keys(&df, &table_schema);
+
+        self.schemas.insert(name.clone(), table_schema);
+        self.tables.insert(name, df);
+        Ok(())
     }
 
-    pub fn from_norm(norm: Normifier) -> Self {
+    fn resolve_foreign_keys(&mut self) {
+        // points every foreign key at the key column of the table it references
+        let key_columns: IndexMap<String, String> = self
+            .schemas
+            .iter()
+            .filter_map(|(name, schema)| Some((name.clone(), schema.primary_key.clone()?)))
+            .collect();
+        for schema in self.schemas.values_mut() {
+            for fk in schema.foreign_keys.iter_mut() {
+                if let Some(key) = key_columns.get(&fk.references) {
+                    fk.referenced_column = key.clone();
+                }
+            }
+        }
+    }
+
+    pub fn schema(&self, table_name: &str) -> Option<&TableSchema> {
+        self.schemas.get(table_name)
+    }
+
+    pub fn relations(&self) -> &RelationGraph {
+        &self.relations
+    }
+
+    pub fn from_norm(norm: Normifier) -> Result<Self> {
         // creates a DataBase struct from a populated Normifier
         let mut this_db: DataBase = DataBase::new();
+        // primary keys configured by rules, looked up while the json paths are still known
+        let key_rules: Vec<(String, String)> = norm
+            .rules
+            .iter()
+            .filter_map(|(path, rule)| {
+                let table: &str = norm.table_for_path(path)?;
+                Some((table.to_string(), rule.primary_key.clone()?))
+            })
+            .collect();
+        this_db.relations = norm.relations;
+        for (name, data) in norm.tables.iter() {
+            for (column, renamed) in data.generated.iter().filter(|(c, r)| c != r) {
+                this_db.relations.rename_fk_column(name, column, renamed);
+            }
+        }
+        this_db.root_is_array = norm.root_is_array;
         for (name, data) in norm.tables.into_iter() {
-            this_db.build_df(name, data);
+            this_db.build_df(name, data)?;
+        }
+        this_db.resolve_foreign_keys();
+        if norm.promote_natural_keys {
+            this_db.promote_natural_keys()?;
+        }
+        for (table, column) in key_rules {
+            this_db.promote_key(&table, &column)?;
         }
-        this_db
+        Ok(this_db)
     }
 }
@@ -0,0 +1,191 @@
+use crate::{
+    database_builder::DataBase,
+    error::{NormError, Result},
+    schema::TableSchema,
+};
+use polars::prelude::*;
+
+/// The SQL flavour `CREATE TABLE` scripts are generated for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    Postgres,
+    // sqlite has no array type, list columns are moved into child tables
+    Sqlite,
+    DuckDb,
+}
+
+impl Dialect {
+    fn supports_arrays(&self) -> bool {
+        !matches!(self, Self::Sqlite)
+    }
+
+    fn supports_unsigned(&self) -> bool {
+        matches!(self, Self::DuckDb)
+    }
+
+    pub fn column_type(&self, dtype: &DataType) -> String {
+        // maps a polars type chosen by `build_series` onto this dialect's column type
+        match self {
+            Self::Sqlite => sqlite_type(dtype).to_string(),
+            Self::Postgres => match dtype {
+                DataType::Boolean => "BOOLEAN".to_string(),
+                DataType::Int8 | DataType::Int16 | DataType::UInt8 => "SMALLINT".to_string(),
+                DataType::Int32 | DataType::UInt16 => "INTEGER".to_string(),
+                DataType::Int64 | DataType::UInt32 => "BIGINT".to_string(),
+                // postgres has no unsigned types, u64 needs 20 decimal digits
+                DataType::UInt64 => "NUMERIC(20, 0)".to_string(),
+                DataType::Float32 => "REAL".to_string(),
+                DataType::Float64 => "DOUBLE PRECISION".to_string(),
+                DataType::List(inner) => format!("{}[]", self.column_type(inner)),
+                _ => "TEXT".to_string(),
+            },
+            Self::DuckDb => match dtype {
+                DataType::Boolean => "BOOLEAN".to_string(),
+                DataType::Int8 => "TINYINT".to_string(),
+                DataType::Int16 => "SMALLINT".to_string(),
+                DataType::Int32 => "INTEGER".to_string(),
+                DataType::Int64 => "BIGINT".to_string(),
+                DataType::UInt8 => "UTINYINT".to_string(),
+                DataType::UInt16 => "USMALLINT".to_string(),
+                DataType::UInt32 => "UINTEGER".to_string(),
+                DataType::UInt64 => "UBIGINT".to_string(),
+                DataType::Float32 => "FLOAT".to_string(),
+                DataType::Float64 => "DOUBLE".to_string(),
+                DataType::List(inner) => format!("{}[]", self.column_type(inner)),
+                _ => "VARCHAR".to_string(),
+            },
+        }
+    }
+}
+
+pub(crate) fn quote_ident(name: &str) -> String {
+    // identifiers come from json keys, so they may contain anything
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+pub(crate) fn sqlite_type(dtype: &DataType) -> &'static str {
+    // maps a polars type onto the sqlite type affinity that stores it without loss
+    match dtype {
+        DataType::Boolean
+        | DataType::UInt8
+        | DataType::UInt16
+        | DataType::UInt32
+        | DataType::UInt64
+        | DataType::Int8
+        | DataType::Int16
+        | DataType::Int32
+        | DataType::Int64 => "INTEGER",
+        DataType::Float32 | DataType::Float64 => "REAL",
+        _ => "TEXT",
+    }
+}
+
+fn signed_type(dtype: &DataType) -> Option<DataType> {
+    match dtype {
+        DataType::UInt64 => Some(DataType::Int64),
+        DataType::List(inner) => signed_type(inner).map(|t| DataType::List(Box::new(t))),
+        _ => None,
+    }
+}
+
+fn narrow_unsigned(schema: &mut TableSchema, df: &DataFrame) {
+    // json integers are parsed as u64 whenever they are non-negative, columns whose values
+    // all fit an i64 are declared signed instead of as a wider numeric type
+    for (column, dtype) in schema.schema.iter_mut() {
+        let Some(signed) = signed_type(dtype) else {
+            continue;
+        };
+        if df
+            .column(column)
+            .is_ok_and(|c| c.strict_cast(&signed).is_ok())
+        {
+            *dtype = signed;
+        }
+    }
+}
+
+pub(crate) fn create_table(name: &str, schema: &TableSchema, dialect: Dialect) -> String {
+    let mut lines: Vec<String> = schema
+        .schema
+        .iter()
+        .map(|(column, dtype)| {
+            let mut line: String =
+                format!("{} {}", quote_ident(column), dialect.column_type(dtype));
+            if schema.is_primary_key(column) {
+                line.push_str(" PRIMARY KEY");
+            } else if !schema.is_nullable(column) {
+                // the column never held a null value
+                line.push_str(" NOT NULL");
+            }
+            line
+        })
+        .collect();
+    for fk in &schema.foreign_keys {
+        lines.push(format!(
+            "FOREIGN KEY ({}) REFERENCES {} ({})",
+            quote_ident(&fk.column),
+            quote_ident(&fk.references),
+            quote_ident(&fk.referenced_column)
+        ));
+    }
+    format!(
+        "CREATE TABLE {} (\n    {}\n);",
+        quote_ident(name),
+        lines.join(",\n    ")
+    )
+}
+
+pub(crate) fn create_indexes(name: &str, schema: &TableSchema) -> Vec<String> {
+    // joins from a parent to its children go through the foreign key columns
+    schema
+        .foreign_keys
+        .iter()
+        .map(|fk| {
+            format!(
+                "CREATE INDEX {} ON {} ({});",
+                quote_ident(&format!("idx_{}_{}", name, fk.column)),
+                quote_ident(name),
+                quote_ident(&fk.column)
+            )
+        })
+        .collect()
+}
+
+impl DataBase {
+    pub(crate) fn for_dialect(&self, dialect: Dialect) -> Result<DataBase> {
+        // dialects without array support get list columns as child tables
+        if dialect.supports_arrays() {
+            Ok(self.clone())
+        } else {
+            self.explode_list_columns()
+        }
+    }
+
+    pub(crate) fn table_schema(&self, name: &str) -> Result<TableSchema> {
+        let df: &DataFrame = self
+            .get(name)
+            .ok_or_else(|| NormError::UnknownTable(name.to_string()))?;
+        Ok(self
+            .schema(name)
+            .cloned()
+            .unwrap_or_else(|| TableSchema::new(df.schema().as_ref().clone())))
+    }
+
+    pub fn to_ddl(&self, dialect: Dialect) -> Result<String> {
+        // a `CREATE TABLE` script for every table, parents before children, followed by
+        // an index on every foreign key column
+        let db: DataBase = self.for_dialect(dialect)?;
+        let mut tables: Vec<String> = vec![];
+        let mut indexes: Vec<String> = vec![];
+        for name in db.relations().parent_first(db.table_names()) {
+            let mut schema: TableSchema = db.table_schema(name)?;
+            if !dialect.supports_unsigned() {
+                narrow_unsigned(&mut schema, &db.tables[name]);
+            }
+            tables.push(create_table(name, &schema, dialect));
+            indexes.extend(create_indexes(name, &schema));
+        }
+        tables.extend(indexes);
+        Ok(tables.join("\n\n") + "\n")
+    }
+}
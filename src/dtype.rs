@@ -1,4 +1,4 @@
-use crate::error;
+use crate::error::{NormError, Result, json_type_name};
 use derive_more::From;
 use serde_json::Value;
 use std::fmt::Display;
@@ -14,6 +14,29 @@ pub enum Dtype {
     Null,
 }
 
+/// The primitive type a column (or the inner values of a list column) is stored as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    Bool,
+    UInt,
+    Int,
+    Float,
+    String,
+}
+
+impl ColumnType {
+    fn promote(self, other: ColumnType) -> Option<ColumnType> {
+        // numeric types widen along UInt -> Int -> Float, every other mix has no common type
+        use ColumnType as CT;
+        match (self, other) {
+            (a, b) if a == b => Some(a),
+            (CT::UInt, CT::Int) | (CT::Int, CT::UInt) => Some(CT::Int),
+            (CT::Float, CT::UInt | CT::Int) | (CT::UInt | CT::Int, CT::Float) => Some(CT::Float),
+            _ => None,
+        }
+    }
+}
+
 impl Display for Dtype {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -23,10 +46,7 @@ impl Display for Dtype {
             Self::Float(fl) => write!(f, "{}", fl),
             Self::UInt(u) => write!(f, "{}", u),
             Self::Int(i) => write!(f, "{}", i),
-            Self::Array(a) => a
-                .iter()
-                .map(|x| write!(f, "{}, ", x.to_string()))
-                .collect::<std::fmt::Result>(),
+            Self::Array(a) => a.iter().try_for_each(|x| write!(f, "{}, ", x)),
         }
     }
 }
@@ -60,6 +80,59 @@ impl Dtype {
         matches!(&self, Dtype::Array(_))
     }
 
+    pub fn column_type(&self) -> Option<ColumnType> {
+        match self {
+            Self::Bool(_) => Some(ColumnType::Bool),
+            Self::UInt(_) => Some(ColumnType::UInt),
+            Self::Int(_) => Some(ColumnType::Int),
+            Self::Float(_) => Some(ColumnType::Float),
+            Self::String(_) => Some(ColumnType::String),
+            Self::Array(_) | Self::Null => None,
+        }
+    }
+
+    pub fn unify<'a>(values: impl IntoIterator<Item = &'a Dtype>) -> Option<ColumnType> {
+        // finds the narrowest type every non-null value can be stored as,
+        // None if the values are all null, nested, or have no common type
+        let mut unified: Option<ColumnType> = None;
+        let mut exceeds_i64: bool = false;
+        for value in values {
+            if value.is_null() {
+                continue;
+            }
+            if let Self::UInt(u) = value {
+                exceeds_i64 |= *u > i64::MAX as u64;
+            }
+            let this_type: ColumnType = value.column_type()?;
+            unified = Some(match unified {
+                None => this_type,
+                Some(current) => current.promote(this_type)?,
+            });
+        }
+        if unified == Some(ColumnType::Int) && exceeds_i64 {
+            // mixing negative values with u64 values above i64::MAX would overflow an Int64
+            return None;
+        }
+        unified
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Self::UInt(u) => i64::try_from(*u).ok(),
+            Self::Int(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::UInt(u) => Some(*u as f64),
+            Self::Int(i) => Some(*i as f64),
+            Self::Float(f) => Some(*f),
+            _ => None,
+        }
+    }
+
     pub fn into_vec(self) -> Option<Vec<Self>> {
         match self {
             Self::Array(arr) => Some(arr),
@@ -67,7 +140,7 @@ impl Dtype {
         }
     }
 
-    pub fn get_slice<'a>(&'a self) -> Option<&'a [Dtype]> {
+    pub fn get_slice(&self) -> Option<&[Dtype]> {
         if let Self::Array(arr) = self {
             Some(arr.as_slice())
         } else {
@@ -75,7 +148,7 @@ impl Dtype {
         }
     }
 
-    pub fn array_is_type(arr: &Vec<Dtype>, check: fn(&Self) -> bool) -> bool {
+    pub fn array_is_type(arr: &[Dtype], check: fn(&Self) -> bool) -> bool {
         arr.iter().all(|i| check(i) || i.is_null())
     }
 
@@ -95,36 +168,40 @@ impl Dtype {
             .collect()
     }
 
-    pub fn from_value(value: Value) -> Self {
+    pub fn from_value(value: Value) -> Result<Self> {
+        // errors carry a path relative to `value`, callers prepend the location of the value itself
         match value {
-            Value::String(s) => Dtype::String(s.to_owned()),
-            Value::Null => Dtype::Null,
+            Value::String(s) => Ok(Dtype::String(s.to_owned())),
+            Value::Null => Ok(Dtype::Null),
             Value::Array(arr) => {
                 if arr.is_empty() {
-                    Dtype::Array(vec![Dtype::Null])
+                    Ok(Dtype::Array(vec![Dtype::Null]))
                 } else {
-                    Dtype::Array(
-                        arr.into_iter()
-                            .map(Dtype::from_value)
-                            .collect::<Vec<Dtype>>(),
-                    )
+                    arr.into_iter()
+                        .enumerate()
+                        .map(|(i, x)| {
+                            Dtype::from_value(x).map_err(|e| e.at_path(&format!("[{}]", i)))
+                        })
+                        .collect::<Result<Vec<Dtype>>>()
+                        .map(Dtype::Array)
                 }
             }
-            Value::Bool(b) => Dtype::Bool(b.to_owned()),
+            Value::Bool(b) => Ok(Dtype::Bool(b.to_owned())),
             Value::Number(n) => {
-                if n.is_u64() {
-                    Dtype::UInt(n.as_u64().unwrap())
-                } else if n.is_i64() {
-                    Dtype::Int(n.as_i64().unwrap())
-                } else if n.is_f64() {
-                    Dtype::Float(n.as_f64().unwrap())
+                if let Some(u) = n.as_u64() {
+                    Ok(Dtype::UInt(u))
+                } else if let Some(i) = n.as_i64() {
+                    Ok(Dtype::Int(i))
+                } else if let Some(f) = n.as_f64() {
+                    Ok(Dtype::Float(f))
                 } else {
-                    Dtype::Null
+                    Ok(Dtype::Null)
                 }
             }
-            Value::Object(_) => {
-                panic!("Cannot convert type Value::Object into Dtype");
-            }
+            Value::Object(_) => Err(NormError::Convert {
+                path: String::new(),
+                found: json_type_name(&value),
+            }),
         }
     }
 }
@@ -1,5 +1,183 @@
+use derive_more::Display;
+use polars::error::PolarsError;
+use serde_json::Value;
+
+pub type Result<T> = std::result::Result<T, NormError>;
+
+#[derive(Debug, Display)]
 pub enum NormError {
-    Convert,
-    Parse,
-    Build,
+    // the input text was not valid json
+    #[display("could not parse json: {_0}")]
+    Parse(serde_json::Error),
+    // the root value was a json primitive
+    #[display("{path}: expected an object or an array at the root, found {found}")]
+    InvalidRoot { path: String, found: &'static str },
+    // an array that is normalized into a table contained something other than objects
+    #[display("{path}: expected an object, found {found}")]
+    ExpectedObject { path: String, found: &'static str },
+    // a json value could not be represented as a column value
+    #[display("{path}: cannot convert {found} into a column value")]
+    Convert { path: String, found: &'static str },
+    // a column or table could not be turned into a polars structure
+    #[display("table `{table}`, column `{column}`: {reason}")]
+    Build {
+        table: String,
+        column: String,
+        reason: String,
+    },
+    // no table with this name exists in the database
+    #[display("no table named `{_0}`")]
+    UnknownTable(String),
+    // a table with this name already exists in the database
+    #[display("a table named `{_0}` already exists")]
+    DuplicateTable(String),
+    // reading or writing a file failed
+    #[display("{path}: {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+    // a table could not be written in the requested format
+    #[display("could not export table `{table}`: {reason}")]
+    Export { table: String, reason: String },
+    // the sqlite database could not be opened or the export transaction failed
+    #[display("sqlite error: {_0}")]
+    Sqlite(rusqlite::Error),
+    // a manifest next to exported tables could not be interpreted
+    #[display("invalid manifest: {_0}")]
+    Manifest(String),
+    // junction detection was asked to see documents after others were already normalized
+    #[display(
+        "junction detection needs every document in one call to `process_value` or `process_json_lines`"
+    )]
+    JunctionDetection,
+    // a normalization rules configuration could not be interpreted
+    #[display("invalid rules: {_0}")]
+    Rules(String),
+}
+
+impl std::error::Error for NormError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Parse(e) => Some(e),
+            Self::Io { source, .. } => Some(source),
+            Self::Sqlite(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for NormError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Parse(e)
+    }
+}
+
+impl From<rusqlite::Error> for NormError {
+    fn from(e: rusqlite::Error) -> Self {
+        Self::Sqlite(e)
+    }
+}
+
+impl NormError {
+    pub(crate) fn build(column: &str, reason: impl ToString) -> Self {
+        // the table is filled in by the caller that knows which dataframe is being built
+        Self::Build {
+            table: String::new(),
+            column: column.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+
+    pub(crate) fn polars(column: &str, e: PolarsError) -> Self {
+        Self::build(column, e)
+    }
+
+    pub(crate) fn io(path: impl AsRef<std::path::Path>, source: std::io::Error) -> Self {
+        Self::Io {
+            path: path.as_ref().display().to_string(),
+            source,
+        }
+    }
+
+    pub(crate) fn export(table_name: &str, reason: impl ToString) -> Self {
+        Self::Export {
+            table: table_name.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+
+    pub(crate) fn in_table(self, table_name: &str) -> Self {
+        match self {
+            Self::Build { column, reason, .. } => Self::Build {
+                table: table_name.to_string(),
+                column,
+                reason,
+            },
+            other => other,
+        }
+    }
+
+    pub(crate) fn in_column(self, column_name: &str) -> Self {
+        match self {
+            Self::Build { table, reason, .. } => Self::Build {
+                table,
+                column: column_name.to_string(),
+                reason,
+            },
+            other => other,
+        }
+    }
+
+    pub(crate) fn at_path(self, prefix: &str) -> Self {
+        // prepends the location of the enclosing value to a path-carrying error
+        match self {
+            Self::Convert { path, found } => Self::Convert {
+                path: format!("{}{}", prefix, path),
+                found,
+            },
+            Self::ExpectedObject { path, found } => Self::ExpectedObject {
+                path: format!("{}{}", prefix, path),
+                found,
+            },
+            other => other,
+        }
+    }
+
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            Self::InvalidRoot { path, .. }
+            | Self::ExpectedObject { path, .. }
+            | Self::Convert { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+
+    pub fn table(&self) -> Option<&str> {
+        match self {
+            Self::Build { table, .. }
+            | Self::Export { table, .. }
+            | Self::UnknownTable(table)
+            | Self::DuplicateTable(table) => Some(table),
+            _ => None,
+        }
+    }
+
+    pub fn column(&self) -> Option<&str> {
+        match self {
+            Self::Build { column, .. } => Some(column),
+            _ => None,
+        }
+    }
+}
+
+pub(crate) fn json_type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
 }
@@ -0,0 +1,340 @@
+use crate::{
+    database_builder::DataBase,
+    error::{NormError, Result},
+    manifest::{MANIFEST_FILE, ManifestTable, build_manifest, parse_manifest},
+    normalizer::{ID_COLUMN, ORDINAL_COLUMN, foreign_key_column},
+    relations::{Cardinality, Relationship},
+    schema::{ForeignKey, TableSchema},
+};
+use polars::prelude::*;
+use serde_json::{Number, Value};
+use std::{fs, path::Path};
+
+/// What to do with list columns when writing a format that only holds scalar cells.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListHandling {
+    // write each list as a json array, e.g. `["a","b"]`
+    Json,
+    // join the list elements with a delimiter, e.g. `a|b`
+    Join(String),
+    // move the list into a `{table}_{column}` child table with one row per element
+    Explode,
+}
+
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    pub lists: ListHandling,
+    pub separator: u8,
+    pub include_header: bool,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            lists: ListHandling::Json,
+            separator: b',',
+            include_header: true,
+        }
+    }
+}
+
+impl CsvOptions {
+    pub fn with_lists(mut self, lists: ListHandling) -> Self {
+        self.lists = lists;
+        self
+    }
+
+    pub fn with_separator(mut self, separator: u8) -> Self {
+        self.separator = separator;
+        self
+    }
+
+    pub fn with_header(mut self, include_header: bool) -> Self {
+        self.include_header = include_header;
+        self
+    }
+}
+
+pub(crate) fn any_value_to_json(value: AnyValue) -> Value {
+    // converts a single polars cell into the json value it was built from
+    match value {
+        AnyValue::Null => Value::Null,
+        AnyValue::Boolean(b) => Value::Bool(b),
+        AnyValue::String(s) => Value::String(s.to_string()),
+        AnyValue::StringOwned(s) => Value::String(s.to_string()),
+        AnyValue::UInt8(u) => Value::from(u),
+        AnyValue::UInt16(u) => Value::from(u),
+        AnyValue::UInt32(u) => Value::from(u),
+        AnyValue::UInt64(u) => Value::from(u),
+        AnyValue::Int8(i) => Value::from(i),
+        AnyValue::Int16(i) => Value::from(i),
+        AnyValue::Int32(i) => Value::from(i),
+        AnyValue::Int64(i) => Value::from(i),
+        AnyValue::Float32(f) => Number::from_f64(f as f64).map_or(Value::Null, Value::Number),
+        AnyValue::Float64(f) => Number::from_f64(f).map_or(Value::Null, Value::Number),
+        AnyValue::List(s) => Value::Array(s.iter().map(any_value_to_json).collect()),
+        other => Value::String(other.to_string()),
+    }
+}
+
+fn list_cells(column: &Column) -> Result<Vec<Option<Series>>> {
+    let lists: &ListChunked = column
+        .list()
+        .map_err(|e| NormError::polars(column.name(), e))?;
+    Ok(lists.into_iter().collect())
+}
+
+fn encode_list_column(column: &Column, lists: &ListHandling) -> Result<Column> {
+    // replaces a list column by a string column holding each list in a single cell
+    let encoded: Vec<Option<String>> = list_cells(column)?
+        .into_iter()
+        .map(|cell| {
+            cell.map(|sub| match lists {
+                ListHandling::Join(delimiter) => sub
+                    .iter()
+                    .map(|v| match v {
+                        AnyValue::Null => String::new(),
+                        AnyValue::String(s) => s.to_string(),
+                        // polars rounds floats for display, rust prints the shortest exact form
+                        AnyValue::Float32(f) => f.to_string(),
+                        AnyValue::Float64(f) => f.to_string(),
+                        other => other.to_string(),
+                    })
+                    .collect::<Vec<String>>()
+                    .join(delimiter),
+                _ => Value::Array(sub.iter().map(any_value_to_json).collect()).to_string(),
+            })
+        })
+        .collect();
+    Ok(Series::new(column.name().clone(), encoded).into_column())
+}
+
+impl DataBase {
+    pub fn list_columns(&self, table_name: &str) -> Vec<String> {
+        // names of the columns built from arrays of json primitives
+        self.get(table_name)
+            .map(|df| {
+                df.get_columns()
+                    .iter()
+                    .filter(|c| matches!(c.dtype(), DataType::List(_)))
+                    .map(|c| c.name().to_string())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn encode_list_columns(&self, lists: &ListHandling) -> Result<DataBase> {
+        // returns a copy of the database in which no table holds a list column
+        if *lists == ListHandling::Explode {
+            return self.explode_list_columns();
+        }
+        let mut encoded: DataBase = self.clone();
+        for (name, df) in encoded.tables.iter_mut() {
+            for column in self.list_columns(name) {
+                let new_column: Column = encode_list_column(
+                    df.column(&column).map_err(|e| NormError::export(name, e))?,
+                    lists,
+                )?;
+                df.with_column(new_column)
+                    .map_err(|e| NormError::export(name, e))?;
+            }
+            if let Some(schema) = encoded.schemas.get_mut(name) {
+                schema.schema = df.schema().as_ref().clone();
+            }
+        }
+        Ok(encoded)
+    }
+
+    pub fn explode_list_columns(&self) -> Result<DataBase> {
+        // moves every list column into a child table holding the parent id, the element's
+        // position in the list, and the element itself
+        let mut exploded: DataBase = DataBase::new();
+        exploded.relations = self.relations.clone();
+        exploded.root_is_array = self.root_is_array;
+        for (name, df) in self.iter() {
+            let list_columns: Vec<String> = self.list_columns(name);
+            let key_column: String = self
+                .schema(name)
+                .and_then(|s| s.primary_key.clone())
+                .unwrap_or_else(|| ID_COLUMN.to_string());
+            let mut parent: DataFrame = df.clone();
+            let mut children: Vec<(String, DataFrame)> = vec![];
+            for column in &list_columns {
+                // a child table never replaces another table, its name takes a numeric suffix
+                let base: String = format!("{}_{}", name, column);
+                let is_taken = |candidate: &str| {
+                    self.tables.contains_key(candidate)
+                        || exploded.tables.contains_key(candidate)
+                        || children.iter().any(|(child, _)| child == candidate)
+                };
+                let child_name: String = if is_taken(&base) {
+                    (2..)
+                        .map(|n| format!("{}_{}", base, n))
+                        .find(|c| !is_taken(c))
+                        .expect("unbounded suffix search")
+                } else {
+                    base
+                };
+                children.push((child_name, explode_column(name, df, &key_column, column)?));
+                parent = parent
+                    .drop(column)
+                    .map_err(|e| NormError::export(name, e))?;
+            }
+
+            let mut schema: TableSchema = self
+                .schema(name)
+                .cloned()
+                .unwrap_or_else(|| TableSchema::new(Schema::default()));
+            schema.schema = parent.schema().as_ref().clone();
+            schema.stringified.retain(|c| !list_columns.contains(c));
+            schema.nullable.retain(|c| !list_columns.contains(c));
+            exploded.schemas.insert(name.clone(), schema);
+            exploded.tables.insert(name.clone(), parent);
+
+            for ((child_name, child), column) in children.into_iter().zip(&list_columns) {
+                let fk: String = foreign_key_column(name);
+                let mut child_schema: TableSchema =
+                    TableSchema::new(child.schema().as_ref().clone());
+                child_schema.foreign_keys.push(ForeignKey {
+                    column: fk.clone(),
+                    references: name.clone(),
+                    referenced_column: key_column.clone(),
+                });
+                child_schema.metadata.push(ORDINAL_COLUMN.to_string());
+                child_schema.ordinal = Some(ORDINAL_COLUMN.to_string());
+                if child.column(column).is_ok_and(|c| c.null_count() > 0) {
+                    child_schema.nullable.push(column.clone());
+                }
+                exploded.relations.add(Relationship {
+                    parent_table: name.clone(),
+                    child_table: child_name.clone(),
+                    fk_column: fk,
+                    cardinality: Cardinality::OneToMany,
+                    json_key: column.clone(),
+                    junction: None,
+                });
+                exploded.schemas.insert(child_name.clone(), child_schema);
+                exploded.tables.insert(child_name, child);
+            }
+        }
+        Ok(exploded)
+    }
+
+    pub fn write_csv(&self, dir: impl AsRef<Path>, options: &CsvOptions) -> Result<()> {
+        // writes `{table}.csv` for every table and a `manifest.json` describing them
+        let dir: &Path = dir.as_ref();
+        fs::create_dir_all(dir).map_err(|e| NormError::io(dir, e))?;
+        let flat: DataBase = self.encode_list_columns(&options.lists)?;
+        for (name, df) in flat.iter() {
+            let path = dir.join(format!("{}.csv", name));
+            let file: fs::File = fs::File::create(&path).map_err(|e| NormError::io(&path, e))?;
+            CsvWriter::new(file)
+                .include_header(options.include_header)
+                .with_separator(options.separator)
+                .finish(&mut df.clone())
+                .map_err(|e| NormError::export(name, e))?;
+        }
+        flat.write_manifest(dir, "csv")
+    }
+    pub fn write_parquet(&self, dir: impl AsRef<Path>) -> Result<()> {
+        // writes `{table}.parquet` for every table and a `manifest.json` describing them
+        let dir: &Path = dir.as_ref();
+        fs::create_dir_all(dir).map_err(|e| NormError::io(dir, e))?;
+        for (name, df) in self.iter() {
+            let path = dir.join(format!("{}.parquet", name));
+            let file: fs::File = fs::File::create(&path).map_err(|e| NormError::io(&path, e))?;
+            // list columns map onto parquet's nested list type, so they are written as-is
+            ParquetWriter::new(file)
+                .finish(&mut df.clone())
+                .map_err(|e| NormError::export(name, e))?;
+        }
+        self.write_manifest(dir, "parquet")
+    }
+
+    pub fn write_ipc(&self, dir: impl AsRef<Path>) -> Result<()> {
+        // writes `{table}.arrow` ipc files and a `manifest.json` that `read_ipc` can load back
+        let dir: &Path = dir.as_ref();
+        fs::create_dir_all(dir).map_err(|e| NormError::io(dir, e))?;
+        for (name, df) in self.iter() {
+            let path = dir.join(format!("{}.arrow", name));
+            let file: fs::File = fs::File::create(&path).map_err(|e| NormError::io(&path, e))?;
+            IpcWriter::new(file)
+                .finish(&mut df.clone())
+                .map_err(|e| NormError::export(name, e))?;
+        }
+        self.write_manifest(dir, "arrow")
+    }
+
+    pub fn read_ipc(dir: impl AsRef<Path>) -> Result<DataBase> {
+        // loads a database written by `write_ipc`, including its schemas and relationships
+        let dir: &Path = dir.as_ref();
+        let manifest_path = dir.join(MANIFEST_FILE);
+        let text: String =
+            fs::read_to_string(&manifest_path).map_err(|e| NormError::io(&manifest_path, e))?;
+        let (tables, relations, root_is_array) = parse_manifest(&serde_json::from_str(&text)?)?;
+
+        let mut db: DataBase = DataBase::new();
+        db.relations = relations;
+        db.root_is_array = root_is_array;
+        for table in tables {
+            let path = dir.join(&table.file);
+            let file: fs::File = fs::File::open(&path).map_err(|e| NormError::io(&path, e))?;
+            let df: DataFrame = IpcReader::new(file)
+                .finish()
+                .map_err(|e| NormError::export(&table.name, e))?;
+            let name: String = table.name.clone();
+            let schema: Schema = df.schema().as_ref().clone();
+            db.schemas
+                .insert(name.clone(), ManifestTable::into_schema(table, schema));
+            db.tables.insert(name, df);
+        }
+        Ok(db)
+    }
+
+    pub(crate) fn write_manifest(&self, dir: &Path, extension: &str) -> Result<()> {
+        let path = dir.join(MANIFEST_FILE);
+        let manifest: String = serde_json::to_string_pretty(&build_manifest(self, extension))?;
+        fs::write(&path, manifest).map_err(|e| NormError::io(&path, e))
+    }
+}
+
+fn explode_column(
+    table: &str,
+    df: &DataFrame,
+    key_column: &str,
+    column: &str,
+) -> Result<DataFrame> {
+    let ids: &Column = df
+        .column(key_column)
+        .map_err(|e| NormError::export(table, e))?;
+    let lists: &Column = df.column(column).map_err(|e| NormError::export(table, e))?;
+    let inner_type: DataType = match lists.dtype() {
+        DataType::List(inner) => inner.as_ref().clone(),
+        other => other.clone(),
+    };
+
+    let mut rows: Vec<IdxSize> = vec![];
+    let mut ordinals: Vec<u64> = vec![];
+    let mut values: Series = Series::new_empty(column.into(), &inner_type);
+    for (row, cell) in list_cells(lists)?.into_iter().enumerate() {
+        let Some(sub) = cell else { continue };
+        for ordinal in 0..sub.len() {
+            rows.push(row as IdxSize);
+            ordinals.push(ordinal as u64);
+        }
+        values
+            .append(&sub)
+            .map_err(|e| NormError::export(table, e))?;
+    }
+
+    let parent_ids: Column = ids
+        .take(&IdxCa::from_vec(PlSmallStr::EMPTY, rows))
+        .map_err(|e| NormError::export(table, e))?
+        .with_name(foreign_key_column(table).into());
+    DataFrame::new(vec![
+        parent_ids,
+        Column::new(ORDINAL_COLUMN.into(), ordinals),
+        values.rechunk().into_column(),
+    ])
+    .map_err(|e| NormError::export(table, e))
+}
@@ -0,0 +1,191 @@
+use crate::dtype::Dtype;
+use crate::error::{NormError, Result};
+use crate::naming::TablePath;
+use indexmap::IndexMap;
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+/// How the `Normifier` assigns the `id` of every row.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum IdStrategy {
+    /// counts rows per table, starting at 1
+    Sequential,
+    /// hashes the object's content together with its parent id and position in its table,
+    /// the same input gets the same ids on every run
+    ContentHash,
+    /// derives a UUIDv5 from the table's json path and the row's index in that table
+    UuidV5,
+    /// a random, time ordered UUIDv7
+    #[default]
+    UuidV7,
+    /// takes the id from the named field of each object, tables whose first object has no
+    /// scalar value under that field use sequential ids instead, a keyed table with a missing,
+    /// duplicate or differently typed key is an error
+    NaturalKey(String),
+}
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(FNV_OFFSET, |hash, b| {
+        (hash ^ *b as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+fn write_canonical(value: &Value, out: &mut String) {
+    // json text with the keys of every object sorted, so key order does not change the hash
+    match value {
+        Value::Object(obj) => {
+            let mut keys: Vec<&String> = obj.keys().collect();
+            keys.sort();
+            out.push('{');
+            for (i, key) in keys.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::String(key.clone()).to_string());
+                out.push(':');
+                write_canonical(&obj[key], out);
+            }
+            out.push('}');
+        }
+        Value::Array(arr) => {
+            out.push('[');
+            for (i, item) in arr.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(item, out);
+            }
+            out.push(']');
+        }
+        other => out.push_str(&other.to_string()),
+    }
+}
+
+fn content_hash(obj: &Map<String, Value>, salt: &str) -> i64 {
+    let mut canonical: String = String::new();
+    write_canonical(&Value::Object(obj.clone()), &mut canonical);
+    canonical.push_str(salt);
+    // kept within i64 so every sql dialect stores it as a native integer
+    (fnv1a(canonical.as_bytes()) & i64::MAX as u64) as i64
+}
+
+/// Hands out row ids according to an `IdStrategy`, counting rows per table.
+#[derive(Debug, Default)]
+pub(crate) struct IdGenerator {
+    pub(crate) strategy: IdStrategy,
+    counters: IndexMap<String, i64>,
+    // per table, the natural keys handed out so far, None for tables using fallback ids
+    natural_keys: IndexMap<String, Option<KeyedTable>>,
+}
+
+/// The natural keys seen in one table, all strings or all scalars of another type.
+#[derive(Debug)]
+struct KeyedTable {
+    is_string: bool,
+    seen: HashSet<String>,
+}
+
+impl IdGenerator {
+    pub(crate) fn new(strategy: IdStrategy) -> Self {
+        Self {
+            strategy,
+            counters: IndexMap::new(),
+            natural_keys: IndexMap::new(),
+        }
+    }
+
+    fn next_index(&mut self, table: &str) -> i64 {
+        let counter: &mut i64 = self.counters.entry(table.to_string()).or_default();
+        *counter += 1;
+        *counter
+    }
+
+    pub(crate) fn shared_id(&self, obj: &Map<String, Value>) -> (Dtype, String) {
+        // the id of an object stored once however often it occurs, and the identity used to
+        // recognise its other occurrences: its natural key if configured, else its content
+        if let IdStrategy::NaturalKey(field) = &self.strategy
+            && let Some(value @ (Value::String(_) | Value::Number(_) | Value::Bool(_))) =
+                obj.get(field)
+            && let Ok(id) = Dtype::from_value(value.clone())
+        {
+            return (id, format!("key:{}", value));
+        }
+        let hash: i64 = content_hash(obj, "");
+        (Dtype::Int(hash), format!("hash:{}", hash))
+    }
+
+    pub(crate) fn next_id(
+        &mut self,
+        path: &TablePath,
+        table: &str,
+        obj: &Map<String, Value>,
+        parent_id: Option<&Dtype>,
+    ) -> Result<Dtype> {
+        let index: i64 = self.next_index(table);
+        Ok(match &self.strategy {
+            IdStrategy::Sequential => Dtype::Int(index),
+            IdStrategy::ContentHash => {
+                // identical objects in one table still need distinct primary keys
+                let parent: String = parent_id.map(Dtype::to_string).unwrap_or_default();
+                let salt: String = format!("\0{}\0{}\0{}", path, parent, index);
+                Dtype::Int(content_hash(obj, &salt))
+            }
+            IdStrategy::UuidV5 => {
+                let name: String = format!("{}[{}]", path, index - 1);
+                Uuid::new_v5(&Uuid::NAMESPACE_OID, name.as_bytes())
+                    .to_string()
+                    .into()
+            }
+            IdStrategy::UuidV7 => Uuid::now_v7().to_string().into(),
+            IdStrategy::NaturalKey(field) => {
+                let field: String = field.clone();
+                self.natural_key(table, &field, obj)?
+                    .unwrap_or(Dtype::Int(index))
+            }
+        })
+    }
+
+    fn natural_key(
+        &mut self,
+        table: &str,
+        field: &str,
+        obj: &Map<String, Value>,
+    ) -> Result<Option<Dtype>> {
+        // the first object of a table decides whether the table is keyed, so generated
+        // fallback ids never share a column with real key values
+        let key: Option<Dtype> = match obj.get(field) {
+            Some(value @ (Value::String(_) | Value::Number(_) | Value::Bool(_))) => {
+                Dtype::from_value(value.clone()).ok()
+            }
+            _ => None,
+        };
+        let keyed: &mut Option<KeyedTable> = self
+            .natural_keys
+            .entry(table.to_string())
+            .or_insert_with(|| {
+                key.as_ref().map(|k| KeyedTable {
+                    is_string: k.is_string(),
+                    seen: HashSet::new(),
+                })
+            });
+        let Some(keyed) = keyed else {
+            return Ok(None);
+        };
+        let error = |reason: String| Err(NormError::build(field, reason).in_table(table));
+        let Some(key) = key else {
+            return error("an object has no natural key value".to_string());
+        };
+        if key.is_string() != keyed.is_string {
+            // string and numeric keys would be coerced to strings in one column
+            return error(format!("natural key {} has a different type", key));
+        }
+        if !keyed.seen.insert(key.to_string()) {
+            return error(format!("duplicate natural key {}", key));
+        }
+        Ok(Some(key))
+    }
+}
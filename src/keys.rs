@@ -0,0 +1,156 @@
+use crate::{
+    database_builder::DataBase,
+    error::{NormError, Result},
+    schema::TableSchema,
+};
+use polars::prelude::*;
+use std::collections::HashMap;
+
+fn can_hold_key(dtype: &DataType) -> bool {
+    // floats, booleans and lists make poor identifiers even when unique
+    dtype.is_integer() || matches!(dtype, DataType::String)
+}
+
+fn looks_like_identifier(column: &str) -> bool {
+    let column: String = column.to_lowercase();
+    column == "id"
+        || column.ends_with("_id")
+        || column.ends_with("uuid")
+        || column.ends_with("_key")
+}
+
+pub(crate) fn candidate_keys(df: &DataFrame, schema: &TableSchema) -> Vec<String> {
+    // json fields whose values are unique and never null, the generated ids and the foreign
+    // keys are keys by construction and are left out
+    if df.height() == 0 {
+        return vec![];
+    }
+    df.get_columns()
+        .iter()
+        .filter(|c| {
+            let name: &str = c.name().as_str();
+            !schema.is_primary_key(name)
+                && schema.foreign_key(name).is_none()
+                && !schema.is_stringified(name)
+                && !schema.is_metadata(name)
+                && can_hold_key(c.dtype())
+                && c.null_count() == 0
+                && c.n_unique().is_ok_and(|n| n == df.height())
+        })
+        .map(|c| c.name().to_string())
+        .collect()
+}
+
+fn key_series(df: &DataFrame, table: &str, column: &str) -> Result<Series> {
+    Ok(df
+        .column(column)
+        .map_err(|e| NormError::polars(column, e).in_table(table))?
+        .as_materialized_series()
+        .rechunk())
+}
+
+impl DataBase {
+    pub fn promote_natural_keys(&mut self) -> Result<()> {
+        // promotes the first identifier-like candidate key of every table, e.g. `id`,
+        // `registrant_id` or `filing_uuid`, tables without one keep their generated ids
+        let promotions: Vec<(String, String)> = self
+            .schemas
+            .iter()
+            .filter_map(|(name, schema)| {
+                schema
+                    .candidate_keys
+                    .iter()
+                    .find(|c| looks_like_identifier(c))
+                    .map(|c| (name.clone(), c.clone()))
+            })
+            .collect();
+        for (table, column) in promotions {
+            self.promote_key(&table, &column)?;
+        }
+        Ok(())
+    }
+
+    pub fn promote_key(&mut self, table_name: &str, column: &str) -> Result<()> {
+        // makes a candidate key the primary key of its table, rewriting the foreign keys that
+        // reference the table to the new key values and dropping the generated id column
+        let schema: &TableSchema = self
+            .schemas
+            .get(table_name)
+            .ok_or_else(|| NormError::UnknownTable(table_name.to_string()))?;
+        if !schema.is_candidate_key(column) {
+            return Err(
+                NormError::build(column, "is not a unique, non-null column").in_table(table_name)
+            );
+        }
+        let old_key: Option<String> = schema.primary_key.clone();
+        let mut df: DataFrame = self
+            .tables
+            .get(table_name)
+            .ok_or_else(|| NormError::UnknownTable(table_name.to_string()))?
+            .clone();
+
+        if let Some(old_key) = &old_key {
+            let old_ids: Series = key_series(&df, table_name, old_key)?;
+            let new_ids: Series = key_series(&df, table_name, column)?;
+            let mapping: HashMap<String, AnyValue<'static>> = old_ids
+                .iter()
+                .zip(new_ids.iter())
+                .map(|(old, new)| (old.to_string(), new.into_static()))
+                .collect();
+            // every foreign key referencing this table, whichever way the relationship points
+            let references: Vec<(String, String)> = self
+                .schemas
+                .iter()
+                .flat_map(|(name, schema)| {
+                    schema
+                        .foreign_keys
+                        .iter()
+                        .filter(|fk| fk.references == table_name)
+                        .map(move |fk| (name.clone(), fk.column.clone()))
+                })
+                .collect();
+            for (child, fk) in references {
+                let Some(child_df) = self.tables.get_mut(&child) else {
+                    continue;
+                };
+                let parents: Vec<AnyValue> = key_series(child_df, &child, &fk)?
+                    .iter()
+                    .map(|id| {
+                        mapping
+                            .get(&id.to_string())
+                            .cloned()
+                            .unwrap_or(AnyValue::Null)
+                    })
+                    .collect();
+                let rewritten: Series = Series::from_any_values(fk.as_str().into(), &parents, true)
+                    .map_err(|e| NormError::polars(&fk, e).in_table(&child))?;
+                child_df
+                    .with_column(rewritten)
+                    .map_err(|e| NormError::polars(&fk, e).in_table(&child))?;
+                let child_schema: Schema = child_df.schema().as_ref().clone();
+                if let Some(child_table) = self.schemas.get_mut(&child) {
+                    child_table.schema = child_schema;
+                    for key in child_table.foreign_keys.iter_mut() {
+                        if key.column == fk && key.references == table_name {
+                            key.referenced_column = column.to_string();
+                        }
+                    }
+                }
+            }
+            if !self.schemas[table_name].is_candidate_key(old_key) {
+                // generated ids carry no information once nothing references them
+                df.drop_in_place(old_key)
+                    .map_err(|e| NormError::polars(old_key, e).in_table(table_name))?;
+            }
+        }
+
+        let schema: &mut TableSchema = self
+            .schemas
+            .get_mut(table_name)
+            .ok_or_else(|| NormError::UnknownTable(table_name.to_string()))?;
+        schema.schema = df.schema().as_ref().clone();
+        schema.primary_key = Some(column.to_string());
+        self.tables.insert(table_name.to_string(), df);
+        Ok(())
+    }
+}
@@ -1,38 +1,85 @@
+//! Turns a nested json structure into a normalized database.
+//!
+//! Every json object becomes a row in a table named after its json path, nested objects and
+//! arrays of objects become child tables linked to their parent through a `{parent}_id`
+//! foreign key. The tables can be inspected as a [`Normifier`] or materialized into polars
+//! dataframes as a [`DataBase`].
+//!
+//! ```no_run
+//! let payload = serde_json::json!({"name": "Acme", "address": {"city": "Austin"}});
+//! let db: normify::DataBase = normify::to_database(payload).unwrap();
+//! for (name, df) in db.iter() {
+//!     println!("{}: {:?}", name, df);
+//! }
+//! ```
 mod database_builder;
+mod ddl;
 mod dtype;
 mod error;
+mod export;
+mod ids;
+mod keys;
+mod manifest;
+mod naming;
 mod normalizer;
+mod pg_copy;
+mod reconstruct;
+mod relations;
+mod rules;
+mod schema;
+mod sqlite;
+mod verify;
 
-pub use normalizer::Normifier;
+pub use database_builder::DataBase;
+pub use ddl::Dialect;
+pub use dtype::{ColumnType, Dtype};
+pub use error::{NormError, Result};
+pub use export::{CsvOptions, ListHandling};
+pub use ids::IdStrategy;
+pub use naming::{NamingStrategy, TablePath};
+pub use normalizer::{AncestorKeys, Normifier, TableData};
+pub use pg_copy::CopyFormat;
+pub use relations::{Cardinality, RelationGraph, Relationship};
+pub use rules::{Action, PathRule, Rules};
+pub use schema::{ForeignKey, TableSchema};
 pub use serde_json::Value;
+pub use verify::{Difference, Loss, VerifyReport};
 
+/// Normalizes a json value into tables, naming the root table `root_table`.
 pub fn from_value(root_value: Value) -> Result<Normifier> {
-    let mut norm_context: Normifier = Normifier::new();
-    let root_name: String = String::from("root_table");
-    norm_context.process_root(root_value, root_name)?;
-    norm_context.clean_normifier();
-    Ok(norm_context)
+    from_value_with_name(root_value, "root_table")
 }
 
+/// Parses json text and normalizes it into tables, naming the root table `root_table`.
 pub fn from_text(content: &str) -> Result<Normifier> {
-    let mut norm_context: Normifier = Normifier::new();
-    let root_value: serde_json::Value = serde_json::from_str(content)?;
-    let root_name: String = String::from("root_table");
-    norm_context.process_root(root_value, root_name)?;
-    norm_context.clean_normifier();
-    Ok(norm_context)
+    from_text_with_name(content, "root_table")
 }
 
+/// Normalizes a json value into tables, naming the root table `root_name`.
 pub fn from_value_with_name(root_value: Value, root_name: &str) -> Result<Normifier> {
     let mut norm_context: Normifier = Normifier::new();
-    norm_context.process_root(root_value, root_name.to_string())?;
-    norm_context.clean_normifier();
+    norm_context.process_value(root_value, root_name)?;
     Ok(norm_context)
 }
+
+/// Parses json text and normalizes it into tables, naming the root table `root_name`.
 pub fn from_text_with_name(content: &str, root_name: &str) -> Result<Normifier> {
-    let mut norm_context: Normifier = Normifier::new();
-    let root_value: serde_json::Value = serde_json::from_str(content)?;
-    norm_context.process_root(root_value, root_name.to_string())?;
-    norm_context.clean_normifier();
-    Ok(norm_context)
+    let root_value: Value = serde_json::from_str(content)?;
+    from_value_with_name(root_value, root_name)
+}
+
+/// Normalizes a json value and builds one polars dataframe per table.
+pub fn to_database(root_value: Value) -> Result<DataBase> {
+    from_value(root_value)?.into_database()
+}
+
+/// Parses json text, normalizes it and builds one polars dataframe per table.
+pub fn text_to_database(content: &str) -> Result<DataBase> {
+    from_text(content)?.into_database()
+}
+
+/// Normalizes a json value, rebuilds it from its tables and reports every difference.
+pub fn verify(root_value: Value) -> Result<VerifyReport> {
+    let rebuilt: Value = to_database(root_value.clone())?.to_json()?;
+    Ok(VerifyReport::compare(&root_value, &rebuilt))
 }
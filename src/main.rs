@@ -1,9 +1,4 @@
-mod normalizer;
-use normalizer::Normifier;
-mod database_builder;
-use database_builder::DataBase;
-mod dtype;
-use serde_json::Value;
+use normify::{DataBase, Value};
 use std::fs;
 use std::io::Read;
 
@@ -14,20 +9,10 @@ fn main() {
         .expect("Unable to read json file");
     let payload: Value =
         serde_json::from_str(&json_content).expect("Could not parse json to Value variant"); // use serde-json to get the json_str as a Value variant
-    let data: Normifier = Normifier::from_value(payload).expect("error parsing data from paylaod");
-    // for (name, data) in data.iter_tables() {
-    //     println!("Table: {}\n", name);
-    //     for (col_name, col) in data.iter_columns() {
-    //         println!("\tColumn: {}", col_name);
-    //         for item in col {
-    //             println!("\t\t{}", item);
-    //         }
-    //     }
-    //     println!();
-    // }
-    let db: DataBase = DataBase::from_norm(data);
+    let db: DataBase = normify::to_database(payload).expect("error parsing data from paylaod");
     println!("{}", db.tables.len());
-    for table in db.tables {
+    for (name, table) in db.iter() {
+        println!("{}", name);
         println!("{:?}", table);
     }
 }
@@ -0,0 +1,223 @@
+use crate::{
+    database_builder::DataBase,
+    error::{NormError, Result},
+    relations::{Cardinality, RelationGraph, Relationship},
+    schema::{ForeignKey, TableSchema},
+};
+use polars::prelude::Schema;
+use serde_json::{Value, json};
+
+pub(crate) const MANIFEST_FILE: &str = "manifest.json";
+
+impl Cardinality {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::OneToOne => "one_to_one",
+            Self::OneToMany => "one_to_many",
+            Self::ManyToOne => "many_to_one",
+            Self::ManyToMany => "many_to_many",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "one_to_one" => Some(Self::OneToOne),
+            "one_to_many" => Some(Self::OneToMany),
+            "many_to_one" => Some(Self::ManyToOne),
+            "many_to_many" => Some(Self::ManyToMany),
+            _ => None,
+        }
+    }
+}
+
+/// A table listed in a manifest, with the metadata needed to rebuild its `TableSchema`.
+#[derive(Debug, Clone)]
+pub(crate) struct ManifestTable {
+    pub(crate) name: String,
+    pub(crate) file: String,
+    pub(crate) primary_key: Option<String>,
+    pub(crate) foreign_keys: Vec<ForeignKey>,
+    pub(crate) stringified: Vec<String>,
+    pub(crate) nullable: Vec<String>,
+    pub(crate) candidate_keys: Vec<String>,
+    pub(crate) metadata: Vec<String>,
+    pub(crate) ordinal: Option<String>,
+}
+
+impl ManifestTable {
+    pub(crate) fn into_schema(self, schema: Schema) -> TableSchema {
+        TableSchema {
+            schema,
+            primary_key: self.primary_key,
+            foreign_keys: self.foreign_keys,
+            stringified: self.stringified,
+            nullable: self.nullable,
+            candidate_keys: self.candidate_keys,
+            metadata: self.metadata,
+            ordinal: self.ordinal,
+        }
+    }
+}
+
+fn field<'a>(value: &'a Value, key: &str) -> Result<&'a Value> {
+    value
+        .get(key)
+        .ok_or_else(|| NormError::Manifest(format!("missing field `{}`", key)))
+}
+
+fn str_field(value: &Value, key: &str) -> Result<String> {
+    field(value, key)?
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| NormError::Manifest(format!("field `{}` is not a string", key)))
+}
+
+fn str_list_field(value: &Value, key: &str) -> Result<Vec<String>> {
+    let Some(items) = value.get(key) else {
+        return Ok(Vec::new());
+    };
+    items
+        .as_array()
+        .ok_or_else(|| NormError::Manifest(format!("field `{}` is not an array", key)))?
+        .iter()
+        .map(|item| {
+            item.as_str()
+                .map(str::to_string)
+                .ok_or_else(|| NormError::Manifest(format!("`{}` holds a non-string", key)))
+        })
+        .collect()
+}
+
+fn array_field<'a>(value: &'a Value, key: &str) -> Result<&'a Vec<Value>> {
+    field(value, key)?
+        .as_array()
+        .ok_or_else(|| NormError::Manifest(format!("field `{}` is not an array", key)))
+}
+
+fn parse_table(value: &Value) -> Result<ManifestTable> {
+    let foreign_keys: Vec<ForeignKey> = match value.get("foreign_keys") {
+        Some(_) => array_field(value, "foreign_keys")?
+            .iter()
+            .map(|fk| {
+                Ok(ForeignKey {
+                    column: str_field(fk, "column")?,
+                    references: str_field(fk, "references")?,
+                    referenced_column: str_field(fk, "referenced_column")?,
+                })
+            })
+            .collect::<Result<Vec<ForeignKey>>>()?,
+        None => Vec::new(),
+    };
+    Ok(ManifestTable {
+        name: str_field(value, "name")?,
+        file: str_field(value, "file")?,
+        primary_key: value
+            .get("primary_key")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        foreign_keys,
+        stringified: str_list_field(value, "stringified")?,
+        nullable: str_list_field(value, "nullable")?,
+        candidate_keys: str_list_field(value, "candidate_keys")?,
+        metadata: str_list_field(value, "metadata")?,
+        ordinal: value
+            .get("ordinal")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+    })
+}
+
+fn parse_relationship(value: &Value) -> Result<Relationship> {
+    let cardinality: String = str_field(value, "cardinality")?;
+    Ok(Relationship {
+        parent_table: str_field(value, "parent_table")?,
+        child_table: str_field(value, "child_table")?,
+        fk_column: str_field(value, "fk_column")?,
+        cardinality: Cardinality::parse(&cardinality)
+            .ok_or_else(|| NormError::Manifest(format!("unknown cardinality `{}`", cardinality)))?,
+        json_key: str_field(value, "json_key")?,
+        junction: value
+            .get("junction")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+    })
+}
+
+pub(crate) fn parse_manifest(
+    manifest: &Value,
+) -> Result<(Vec<ManifestTable>, RelationGraph, bool)> {
+    // the inverse of `build_manifest`
+    let tables: Vec<ManifestTable> = array_field(manifest, "tables")?
+        .iter()
+        .map(parse_table)
+        .collect::<Result<Vec<ManifestTable>>>()?;
+    let mut relations: RelationGraph = RelationGraph::new();
+    for r in array_field(manifest, "relationships")? {
+        relations.add(parse_relationship(r)?);
+    }
+    let root_is_array: bool = manifest
+        .get("root_is_array")
+        .and_then(Value::as_bool)
+        .unwrap_or(false);
+    Ok((tables, relations, root_is_array))
+}
+
+fn foreign_key_json(fk: &ForeignKey) -> Value {
+    json!({
+        "column": fk.column,
+        "references": fk.references,
+        "referenced_column": fk.referenced_column,
+    })
+}
+
+fn table_json(name: &str, file: &str, rows: usize, schema: Option<&TableSchema>) -> Value {
+    let Some(schema) = schema else {
+        return json!({"name": name, "file": file, "rows": rows});
+    };
+    json!({
+        "name": name,
+        "file": file,
+        "rows": rows,
+        "columns": schema
+            .schema
+            .iter()
+            .map(|(column, dtype)| json!({"name": column.as_str(), "dtype": dtype.to_string()}))
+            .collect::<Vec<Value>>(),
+        "primary_key": schema.primary_key,
+        "foreign_keys": schema.foreign_keys.iter().map(foreign_key_json).collect::<Vec<Value>>(),
+        "stringified": schema.stringified,
+        "nullable": schema.nullable,
+        "candidate_keys": schema.candidate_keys,
+        "metadata": schema.metadata,
+        "ordinal": schema.ordinal,
+    })
+}
+
+fn relationship_json(r: &Relationship) -> Value {
+    json!({
+        "parent_table": r.parent_table,
+        "child_table": r.child_table,
+        "fk_column": r.fk_column,
+        "cardinality": r.cardinality.as_str(),
+        "json_key": r.json_key,
+        "junction": r.junction,
+    })
+}
+
+pub(crate) fn build_manifest(db: &DataBase, extension: &str) -> Value {
+    // describes every table written next to the manifest, plus the links between them
+    let tables: Vec<Value> = db
+        .iter()
+        .map(|(name, df)| {
+            let file: String = format!("{}.{}", name, extension);
+            table_json(name, &file, df.height(), db.schema(name))
+        })
+        .collect();
+    let relationships: Vec<Value> = db.relations().iter().map(relationship_json).collect();
+    json!({
+        "format": extension,
+        "root_is_array": db.root_is_array,
+        "tables": tables,
+        "relationships": relationships,
+    })
+}
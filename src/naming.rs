@@ -0,0 +1,207 @@
+use crate::error::{NormError, Result};
+use indexmap::IndexMap;
+use std::fmt::{Debug, Display};
+
+/// The chain of JSON keys leading from the root value to a nested object.
+/// Array elements do not add a segment, so every object in `$.lobbyists` shares one path.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TablePath {
+    root: String,
+    keys: Vec<String>,
+}
+
+impl Display for TablePath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "$")?;
+        for k in &self.keys {
+            write!(f, ".{}", k)?;
+        }
+        Ok(())
+    }
+}
+
+impl TablePath {
+    pub fn root(root_name: &str) -> Self {
+        Self {
+            root: root_name.to_string(),
+            keys: Vec::new(),
+        }
+    }
+
+    pub fn child(&self, key: &str) -> Self {
+        let mut keys: Vec<String> = self.keys.clone();
+        keys.push(key.to_string());
+        Self {
+            root: self.root.clone(),
+            keys,
+        }
+    }
+
+    pub(crate) fn descendant(&self, keys: &[String]) -> Self {
+        let mut path: TablePath = self.clone();
+        path.keys.extend_from_slice(keys);
+        path
+    }
+
+    pub fn root_name(&self) -> &str {
+        &self.root
+    }
+
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    pub fn leaf(&self) -> Option<&str> {
+        self.keys.last().map(String::as_str)
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    fn root_stem(&self) -> &str {
+        // "root_table" contributes "root" to path-qualified names
+        self.root.strip_suffix("_table").unwrap_or(&self.root)
+    }
+}
+
+/// How the `Normifier` derives a table name from the JSON path of a nested object.
+#[derive(Default)]
+pub enum NamingStrategy {
+    /// joins the root name and every key on the path, e.g. `root_client_address`
+    #[default]
+    PathQualified,
+    /// names the table after the last key (`address_table`), prefixing ancestor keys
+    /// only when another path already claimed that name
+    LeafOnly,
+    /// delegates to a caller supplied closure, collisions are still disambiguated
+    Custom(Box<dyn Fn(&TablePath) -> String + Send + Sync>),
+}
+
+impl Debug for NamingStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::PathQualified => write!(f, "PathQualified"),
+            Self::LeafOnly => write!(f, "LeafOnly"),
+            Self::Custom(_) => write!(f, "Custom(..)"),
+        }
+    }
+}
+
+impl NamingStrategy {
+    pub fn custom<F>(f: F) -> Self
+    where
+        F: Fn(&TablePath) -> String + Send + Sync + 'static,
+    {
+        Self::Custom(Box::new(f))
+    }
+
+    fn candidates(&self, path: &TablePath) -> Vec<String> {
+        // returns the preferred name first, followed by progressively more qualified fallbacks
+        match self {
+            Self::PathQualified => {
+                let mut parts: Vec<&str> = vec![path.root_stem()];
+                parts.extend(path.keys().iter().map(String::as_str));
+                vec![parts.join("_")]
+            }
+            Self::LeafOnly => {
+                let keys: &[String] = path.keys();
+                let mut names: Vec<String> = (1..=keys.len())
+                    .map(|n| format!("{}_table", keys[keys.len() - n..].join("_")))
+                    .collect();
+                names.push(format!("{}_{}_table", path.root_stem(), keys.join("_")));
+                names
+            }
+            Self::Custom(f) => vec![f(path)],
+        }
+    }
+}
+
+/// Assigns table names to JSON paths and remembers every assignment.
+#[derive(Debug, Default)]
+pub struct TableNamer {
+    pub(crate) strategy: NamingStrategy,
+    pub(crate) assigned: IndexMap<TablePath, String>,
+    // table names configured for display paths, e.g. `$.lobbyists`, taking precedence over the strategy
+    pub(crate) renames: IndexMap<String, String>,
+    // the junction table linking the objects of each path to their parents
+    pub(crate) junctions: IndexMap<TablePath, String>,
+}
+
+impl TableNamer {
+    pub fn new(strategy: NamingStrategy) -> Self {
+        Self {
+            strategy,
+            assigned: IndexMap::new(),
+            renames: IndexMap::new(),
+            junctions: IndexMap::new(),
+        }
+    }
+
+    fn is_assigned(&self, name: &str) -> bool {
+        self.assigned
+            .values()
+            .chain(self.junctions.values())
+            .any(|n| n == name)
+    }
+
+    fn is_taken(&self, name: &str) -> bool {
+        // names configured for a path stay free for that path
+        self.is_assigned(name) || self.renames.values().any(|n| n == name)
+    }
+
+    pub(crate) fn name_for(&mut self, path: &TablePath) -> Result<String> {
+        // the same path always maps to the same table
+        if let Some(name) = self.assigned.get(path) {
+            return Ok(name.clone());
+        }
+        let name: String = if let Some(renamed) = self.renames.get(&path.to_string()) {
+            if self.is_assigned(renamed) {
+                return Err(NormError::Rules(format!(
+                    "`{}`: table name `{}` is already used by another path",
+                    path, renamed
+                )));
+            }
+            renamed.clone()
+        } else if path.is_root() {
+            path.root_name().to_string()
+        } else {
+            let candidates: Vec<String> = self.strategy.candidates(path);
+            match candidates.iter().find(|c| !self.is_taken(c)) {
+                Some(free) => free.clone(),
+                None => {
+                    // every candidate collides with another path, fall back to a numeric suffix
+                    let base: &String = candidates.last().expect("at least one candidate name");
+                    (2..)
+                        .map(|n| format!("{}_{}", base, n))
+                        .find(|c| !self.is_taken(c))
+                        .expect("unbounded suffix search")
+                }
+            }
+        };
+        self.assigned.insert(path.clone(), name.clone());
+        Ok(name)
+    }
+
+    pub(crate) fn junction_for(&mut self, path: &TablePath, table: &str) -> String {
+        // the junction table of a path is `{table}_link`, suffixed when another table has it
+        if let Some(name) = self.junctions.get(path) {
+            return name.clone();
+        }
+        let base: String = format!("{}_link", table);
+        let name: String = if self.is_taken(&base) {
+            (2..)
+                .map(|n| format!("{}_{}", base, n))
+                .find(|c| !self.is_taken(c))
+                .expect("unbounded suffix search")
+        } else {
+            base
+        };
+        self.junctions.insert(path.clone(), name.clone());
+        name
+    }
+
+    pub fn iter(&self) -> indexmap::map::Iter<'_, TablePath, String> {
+        self.assigned.iter()
+    }
+}
@@ -1,34 +1,167 @@
+use crate::database_builder::DataBase;
 use crate::dtype::Dtype;
-use anyhow::Result;
+use crate::error::{NormError, Result, json_type_name};
+use crate::ids::{IdGenerator, IdStrategy};
+use crate::naming::{NamingStrategy, TableNamer, TablePath};
+use crate::relations::{Cardinality, RelationGraph, Relationship};
+use crate::rules::{Action, Rules};
 use indexmap::{IndexMap, map::Iter};
 use serde_json::{Map, Value};
-use uuid::Uuid;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 
 #[derive(Debug)]
 pub struct TableData {
     pub(crate) columns: IndexMap<String, Vec<Dtype>>,
+    pub(crate) n_rows: usize,
+    // foreign key column name mapped to the table it references
+    pub(crate) foreign_keys: IndexMap<String, String>,
+    // the column holding the generated row ids, `id` unless the json uses that key itself
+    pub(crate) key_column: String,
+    // columns whose names were not taken from a json key, by what they hold, mapped to the
+    // name they were given in this table
+    pub(crate) generated: IndexMap<String, String>,
 }
 
-#[derive(Debug)]
+/// Which ancestor tables, beyond the direct parent, every row holds a foreign key to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AncestorKeys {
+    /// only the `{parent}_id` of the direct parent
+    #[default]
+    Parent,
+    /// also the id of the root record the row is nested in
+    Root,
+    /// also the id of every table between the root and the parent
+    All,
+}
+
+/// A value of a row and the keys leading to it from the row's object, more than one for the
+/// fields of inlined objects.
+type Field<'v> = (Cow<'v, [String]>, &'v Value);
+
+pub(crate) const ID_COLUMN: &str = "id";
+// the position of a row within the json array it came from
+pub(crate) const ORDINAL_COLUMN: &str = "_ord";
+// the provenance of a row: its json pointer, source and record number within that source
+pub(crate) const POINTER_COLUMN: &str = "_pointer";
+pub(crate) const SOURCE_COLUMN: &str = "_source";
+pub(crate) const RECORD_COLUMN: &str = "_record";
+pub(crate) const METADATA_COLUMNS: [&str; 4] =
+    [ORDINAL_COLUMN, POINTER_COLUMN, SOURCE_COLUMN, RECORD_COLUMN];
+
+pub(crate) fn foreign_key_column(parent_table: &str) -> String {
+    // the column a child table uses to reference its parent
+    format!("{}_{}", parent_table, ID_COLUMN)
+}
+
+#[derive(Debug, Default)]
 pub struct Normifier {
     pub(crate) tables: IndexMap<String, TableData>,
-    // relations: Vec<Relationship>,
+    pub(crate) namer: TableNamer,
+    pub(crate) ids: IdGenerator,
+    pub(crate) relations: RelationGraph,
+    // whether the root value was an array of records rather than a single object
+    pub(crate) root_is_array: bool,
+    pub(crate) promote_natural_keys: bool,
+    // whether nested objects are stored once per distinct content
+    pub(crate) dedup: bool,
+    // the identities already stored in every shared table
+    pub(crate) shared_rows: IndexMap<String, HashSet<String>>,
+    // json paths, e.g. `$.lobbyists`, whose arrays are linked through a junction table
+    pub(crate) junction_paths: HashSet<String>,
+    // whether arrays sharing an object between parents are linked through a junction table
+    pub(crate) detect_junctions: bool,
+    // whether child rows from arrays record their array position in an `_ord` column
+    pub(crate) ordinals: bool,
+    // the array position of the object about to be parsed, taken by its row
+    pending_ordinal: Option<usize>,
+    // whether every row records where in the input it came from
+    pub(crate) provenance: bool,
+    // the reference tokens leading to the value being parsed
+    pointer: Vec<String>,
+    // the source and record number of the document being parsed
+    source: Option<(String, usize)>,
+    pub(crate) ancestor_keys: AncestorKeys,
+    // how many levels of nested objects are inlined into their row as prefixed columns
+    pub(crate) flatten_depth: usize,
+    // json paths, e.g. `$.address`, whose objects are always inlined into their row
+    pub(crate) flatten_paths: HashSet<String>,
+    // per-path actions, table names and primary keys that override the other settings
+    pub(crate) rules: Rules,
+    // the table and id of every row enclosing the object being parsed, root first
+    ancestors: Vec<(String, Dtype)>,
 }
 
 impl TableData {
     fn new() -> Self {
         Self {
             columns: IndexMap::new(),
+            n_rows: 0,
+            foreign_keys: IndexMap::new(),
+            key_column: ID_COLUMN.to_string(),
+            generated: IndexMap::new(),
+        }
+    }
+
+    fn generated_column(&mut self, identity: &str, name: &str, fields: &[String]) -> String {
+        // moves a generated column to an underscore prefixed name whenever a json key of the
+        // row, or a column of the table it has not used before, has the same name, so the
+        // source value is kept instead of overwritten
+        let existing: Option<&String> = self.generated.get(identity);
+        let is_new: bool = existing.is_none();
+        let mut column: String = existing.cloned().unwrap_or_else(|| name.to_string());
+        if fields.contains(&column) || (is_new && self.columns.contains_key(&column)) {
+            let mut renamed: String = format!("_{}", column);
+            while fields.contains(&renamed) || self.columns.contains_key(&renamed) {
+                renamed.insert(0, '_');
+            }
+            if !is_new && let Some(idx) = self.columns.get_index_of(&column) {
+                let (_, values) = self
+                    .columns
+                    .shift_remove_index(idx)
+                    .expect("index of an existing column");
+                self.columns.shift_insert(idx, renamed.clone(), values);
+            }
+            if let Some((idx, _, references)) = self.foreign_keys.shift_remove_full(&column) {
+                self.foreign_keys
+                    .shift_insert(idx, renamed.clone(), references);
+            }
+            column = renamed;
         }
+        self.generated.insert(identity.to_string(), column.clone());
+        column
     }
+
     fn extend_column(&mut self, col_name: String, col_data: Dtype) {
-        // pushes a value into its appropriate column vector or creates a new vector
+        // pushes a value into its appropriate column vector or creates a new vector,
+        // back-filling nulls for every row that was added before this column first appeared
+        let n_rows: usize = self.n_rows;
         self.columns
             .entry(col_name)
-            .or_insert_with(Vec::new)
+            .or_insert_with(|| vec![Dtype::Null; n_rows])
             .push(col_data);
     }
 
+    fn push_record(&mut self, record: IndexMap<String, Dtype>) {
+        // appends exactly one cell per column so that every column stays row-aligned
+        for (field, data) in record {
+            self.extend_column(field, data);
+        }
+        self.n_rows += 1;
+        for column in self.columns.values_mut() {
+            // pad the columns this record did not contain
+            column.resize(self.n_rows, Dtype::Null);
+        }
+    }
+
+    pub fn n_rows(&self) -> usize {
+        self.n_rows
+    }
+
+    pub fn iter_foreign_keys<'a>(&'a self) -> Iter<'a, String, String> {
+        self.foreign_keys.iter()
+    }
+
     pub fn iter_columns<'a>(&'a self) -> Iter<'a, String, Vec<Dtype>> {
         self.columns.iter()
     }
@@ -47,17 +180,186 @@ impl TableData {
 
 impl Normifier {
     pub fn new() -> Self {
-        Self {
-            tables: IndexMap::new(),
+        Self::default()
+    }
+
+    pub fn with_naming(mut self, strategy: NamingStrategy) -> Self {
+        // selects how nested objects are mapped to table names
+        // table names configured by rules are kept
+        let renames: IndexMap<String, String> = std::mem::take(&mut self.namer.renames);
+        self.namer = TableNamer::new(strategy);
+        self.namer.renames = renames;
+        self
+    }
+
+    pub fn with_id_strategy(mut self, strategy: IdStrategy) -> Self {
+        // selects how the `id` of every row is generated
+        self.ids = IdGenerator::new(strategy);
+        self
+    }
+
+    pub fn with_natural_keys(mut self, promote: bool) -> Self {
+        // makes unique, identifier-like columns the primary key in place of the generated ids
+        self.promote_natural_keys = promote;
+        self
+    }
+
+    pub fn with_dedup(mut self, dedup: bool) -> Self {
+        // stores identical nested objects once, objects are then referenced many-to-one by
+        // their parent and arrays of objects many-to-many through a junction table
+        self.dedup = dedup;
+        self
+    }
+
+    pub fn with_ordinals(mut self, ordinals: bool) -> Self {
+        // adds an `_ord` column holding the array index to every child table built from an array
+        self.ordinals = ordinals;
+        self
+    }
+
+    pub fn with_provenance(mut self, provenance: bool) -> Self {
+        // adds a `_pointer` column holding the json pointer of each row's object, and for
+        // documents given to `process_document` a `_source` and `_record` column
+        self.provenance = provenance;
+        self
+    }
+
+    pub fn with_ancestor_keys(mut self, ancestor_keys: AncestorKeys) -> Self {
+        // gives every descendant table a direct foreign key to its root record, or to all of
+        // its ancestors, so they can be joined without going through the tables in between
+        self.ancestor_keys = ancestor_keys;
+        self
+    }
+
+    pub fn with_flatten_depth(mut self, depth: usize) -> Self {
+        // inlines objects up to `depth` levels below a row as prefixed columns, e.g.
+        // `address_city`, instead of one-to-one child tables, arrays still become tables.
+        // a prefixed name already used by a json key gets a leading underscore. flattening
+        // is not reversed: `DataBase::to_json` keeps the prefixed keys
+        self.flatten_depth = depth;
+        self
+    }
+
+    pub fn with_flatten(mut self, path: &str) -> Self {
+        // inlines the objects at `path`, e.g. `$.address`, into their row as prefixed columns
+        self.flatten_paths.insert(path.to_string());
+        self
+    }
+
+    pub fn with_rules(mut self, rules: Rules) -> Self {
+        // applies per-path rules while normalizing, see `Rules` for the configuration format
+        for (path, rule) in rules.iter() {
+            if let Some(name) = &rule.rename {
+                self.namer.renames.insert(path.clone(), name.clone());
+            }
+        }
+        self.rules = rules;
+        self
+    }
+
+    pub fn with_junction(mut self, path: &str) -> Self {
+        // links the array of objects at `path`, e.g. `$.lobbyists`, to its parents through a
+        // junction table instead of a parent foreign key in the child table
+        self.junction_paths.insert(path.to_string());
+        self
+    }
+
+    pub fn with_junction_detection(mut self, detect: bool) -> Self {
+        // uses a junction table for every array path where the same object, by natural key or
+        // content, occurs under more than one parent, looking at all the documents of a single
+        // `process_value` or `process_json_lines` call
+        self.detect_junctions = detect;
+        self
+    }
+
+    pub fn process_value(&mut self, root_value: Value, root_name: &str) -> Result<()> {
+        // normalizes a json value with this normifier's configuration, a value that fails
+        // keeps the rows parsed before the error, including rows of tables it created, so
+        // callers needing all or nothing should validate a value before processing it
+        if self.detect_junctions {
+            self.find_shared_arrays([&root_value], root_name)?;
+        }
+        let result: Result<()> = self.process_root(root_value, root_name.to_string());
+        // an error leaves the traversal mid-way, the next value starts again from its root
+        self.pointer.clear();
+        self.ancestors.clear();
+        self.pending_ordinal = None;
+        self.clean_normifier();
+        result
+    }
+
+    pub fn process_document(
+        &mut self,
+        root_value: Value,
+        root_name: &str,
+        source: &str,
+        record: usize,
+    ) -> Result<()> {
+        // normalizes one of several documents into the same tables, `source` names where the
+        // document came from, e.g. its file, and `record` its position within that source
+        self.source = Some((source.to_string(), record));
+        let result: Result<()> = self.process_value(root_value, root_name);
+        self.source = None;
+        result
+    }
+
+    pub fn process_json_lines(
+        &mut self,
+        content: &str,
+        root_name: &str,
+        source: &str,
+    ) -> Result<()> {
+        // normalizes newline delimited json, every non-blank line is one record numbered by
+        // its line in `source`
+        let mut documents: Vec<(usize, Value)> = vec![];
+        for (i, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            documents.push((i + 1, serde_json::from_str(line)?));
+        }
+        // shared objects are detected across every line before the first one is normalized,
+        // so each array path keeps one shape for the whole input
+        if self.detect_junctions {
+            self.find_shared_arrays(documents.iter().map(|(_, v)| v), root_name)?;
         }
+        let detect: bool = std::mem::replace(&mut self.detect_junctions, false);
+        let result: Result<()> = documents.into_iter().try_for_each(|(record, root_value)| {
+            self.process_document(root_value, root_name, source, record)
+        });
+        self.detect_junctions = detect;
+        result?;
+        self.root_is_array = true;
+        Ok(())
+    }
+
+    pub fn relations(&self) -> &RelationGraph {
+        // the parent/child links between the generated tables
+        &self.relations
+    }
+
+    pub fn into_database(self) -> Result<DataBase> {
+        // materializes every table as a polars dataframe
+        DataBase::from_norm(self)
+    }
+
+    pub fn iter_table_paths<'a>(&'a self) -> Iter<'a, TablePath, String> {
+        // the json path each table was created from, in creation order
+        self.namer.iter()
+    }
+
+    pub fn table_for_path(&self, path: &str) -> Option<&str> {
+        // looks up a table by the display form of its json path, e.g. `$.client.address`
+        self.namer
+            .iter()
+            .find(|(p, _)| p.to_string() == path)
+            .map(|(_, name)| name.as_str())
     }
 
     pub fn add_record(&mut self, table_name: String, record: IndexMap<String, Dtype>) {
         // inserts a row of data into its corresponding table
         let table: &mut TableData = self.tables.entry(table_name).or_insert_with(TableData::new);
-        for (field, data) in record {
-            table.extend_column(field, data);
-        }
+        table.push_record(record);
     }
     pub fn iter_tables<'a>(&'a self) -> Iter<'a, String, TableData> {
         self.tables.iter()
@@ -65,84 +367,529 @@ impl Normifier {
 
     pub(crate) fn parse_object(
         &mut self,
+        path: &TablePath,
+        t_name: &String,
+        obj: &Map<String, Value>,
+        p_id: Option<&Dtype>,
+        pt_name: Option<&String>,
+    ) -> Result<()> {
+        // creates the id of this row with the configured strategy
+        let this_id: Dtype = self.ids.next_id(path, t_name, obj, p_id)?;
+        self.parse_row(path, t_name, obj, this_id, p_id, pt_name)
+    }
+
+    fn parse_shared(
+        &mut self,
+        path: &TablePath,
+        t_name: &String,
+        obj: &Map<String, Value>,
+    ) -> Result<Dtype> {
+        // stores an object the first time it is seen, every occurrence shares its id
+        let (this_id, identity) = self.ids.shared_id(obj);
+        if self
+            .shared_rows
+            .entry(t_name.to_owned())
+            .or_default()
+            .insert(identity)
+        {
+            // a shared row has no single ancestry, its descendants treat it as their root
+            let ancestors: Vec<(String, Dtype)> = std::mem::take(&mut self.ancestors);
+            self.parse_row(path, t_name, obj, this_id.clone(), None, None)?;
+            self.ancestors = ancestors;
+        }
+        Ok(this_id)
+    }
+
+    fn parse_row(
+        &mut self,
+        path: &TablePath,
         t_name: &String,
         obj: &Map<String, Value>,
-        p_id: Option<&String>,
+        this_id: Dtype,
+        p_id: Option<&Dtype>,
         pt_name: Option<&String>,
     ) -> Result<()> {
         // TODO log table name
+        // registers the table before recursing so tables are ordered parent-first
+        let fields: Vec<Field> = self.row_fields(path, obj);
+        let this_table: &mut TableData = self
+            .tables
+            .entry(t_name.to_owned())
+            .or_insert_with(TableData::new);
+        // json keys keep their names, inlined fields make way for them
+        let mut columns: Vec<String> = fields
+            .iter()
+            .map(|(tokens, _)| match tokens.as_ref() {
+                [key] => key.clone(),
+                _ => String::new(),
+            })
+            .collect();
+        for (i, (tokens, _)) in fields.iter().enumerate() {
+            if tokens.len() > 1 {
+                let column: String =
+                    this_table.generated_column(&tokens.join("."), &tokens.join("_"), &columns);
+                columns[i] = column;
+            }
+        }
+        let key_column: String = this_table.generated_column(ID_COLUMN, ID_COLUMN, &columns);
+        this_table.key_column = key_column.clone();
+        let fk_column: Option<String> = pt_name.map(|pname| {
+            let fk: String = foreign_key_column(pname);
+            this_table.generated_column(&fk, &fk, &columns)
+        });
+        if let (Some(fk), Some(pname)) = (&fk_column, pt_name) {
+            this_table
+                .foreign_keys
+                .entry(fk.to_owned())
+                .or_insert_with(|| pname.to_owned());
+        }
         // creates a new index map to hold a row of data
         let mut this_record: IndexMap<String, Dtype> = IndexMap::new();
-        // creates a new random id for this row
-        let this_id = Uuid::now_v7().to_string();
-        // this_table.extend_column("id".to_string(), this_id.clone().into());
-        this_record.insert("id".to_string(), this_id.clone().into());
+        this_record.insert(key_column, this_id.clone());
+        self.insert_ancestor_keys(t_name, &mut this_record, &columns);
 
-        if let (Some(pname), Some(pid)) = (pt_name, p_id) {
+        if let (Some(fk), Some(pid)) = (fk_column, p_id) {
             // if the table this row belongs to has a parent table, insert the parent id as a foreign key
-            this_record.insert(format!("{}_id", pname), pid.to_owned().into());
+            this_record.insert(fk, pid.clone());
+        }
+        if let Some(ordinal) = self.pending_ordinal.take() {
+            let column: String = self.generated_column(t_name, ORDINAL_COLUMN, &columns);
+            this_record.insert(column, Dtype::UInt(ordinal as u64));
+        }
+        if self.provenance {
+            let column: String = self.generated_column(t_name, POINTER_COLUMN, &columns);
+            this_record.insert(column, self.json_pointer().into());
+            if let Some((source, record)) = self.source.clone() {
+                let column: String = self.generated_column(t_name, SOURCE_COLUMN, &columns);
+                this_record.insert(column, source.into());
+                let column: String = self.generated_column(t_name, RECORD_COLUMN, &columns);
+                this_record.insert(column, Dtype::UInt(record as u64));
+            }
         }
 
-        for (k, v) in obj {
-            // iterate through each property and its value
+        if self.ancestor_keys != AncestorKeys::Parent {
+            self.ancestors.push((t_name.to_owned(), this_id.clone()));
+        }
+        for ((tokens, v), column) in fields.iter().zip(columns.iter().cloned()) {
+            // iterate through each property and its value, fields of inlined objects are
+            // prefixed with the keys leading to them, e.g. `address_city`
+            let tokens: &[String] = tokens.as_ref();
+            let key: Cow<str> = match tokens {
+                [key] => Cow::Borrowed(key),
+                _ => Cow::Owned(tokens.join("_")),
+            };
+            let k: &str = &key;
+            let action: Option<Action> = self.field_action(path, tokens);
             match v {
+                Value::Object(_) | Value::Array(_) if action == Some(Action::JsonString) => {
+                    // kept whole as json text
+                    this_record.insert(column, Dtype::String(v.to_string()));
+                }
                 Value::Array(arr) => {
                     if arr.is_empty() {
-                        this_record.insert(k.to_string(), Dtype::Null);
+                        this_record.insert(column, Dtype::Null);
                     }
                     // if the value is an array, this signifies the possible creation of a new table,
                     // where the current table has a one-to-many relationship with the new table
                     else if arr.iter().all(Value::is_object) {
                         // if every item is an object, this value becomes a new table
-                        // new table name created from property name
-                        let child_table: String = format!("{}_table", k);
-                        self.parse_object_array(&child_table, arr, Some(t_name), Some(&this_id))?
+                        // new table name derived from the property's json path
+                        let child_path: TablePath = path.descendant(tokens);
+                        let child_table: String = self.namer.name_for(&child_path)?;
+                        self.enter(tokens);
+                        if self.dedup || self.junction_paths.contains(&child_path.to_string()) {
+                            self.parse_junction(
+                                &child_path,
+                                &child_table,
+                                arr,
+                                t_name,
+                                &this_id,
+                                k,
+                            )?
+                        } else {
+                            self.relate(t_name, &child_table, k, Cardinality::OneToMany);
+                            self.parse_object_array(
+                                &child_path,
+                                &child_table,
+                                arr,
+                                Some(t_name),
+                                Some(&this_id),
+                            )?
+                        }
+                        self.leave(tokens);
+                    } else if action == Some(Action::Explode) {
+                        self.explode(&path.descendant(tokens), k, arr, t_name, &this_id)?;
                     } else {
                         // if the array is an array of json primitives, just insert the array into the row container
-                        this_record.insert(k.to_string(), Dtype::from_value(v.to_owned()));
+                        this_record.insert(column, Self::to_dtype(path, tokens, v)?);
                     }
                 }
                 Value::Object(child) => {
                     // if the value is an object, this is a new table
                     // the current table has a one-to-one relationship with the new table
-                    let new_tname: String = format!("{}_table", k);
-                    self.parse_object(&new_tname, child, Some(&this_id), Some(t_name))?;
+                    let child_path: TablePath = path.descendant(tokens);
+                    let new_tname: String = self.namer.name_for(&child_path)?;
+                    self.enter(tokens);
+                    if self.dedup {
+                        // the parent references the shared row, many parents to one child
+                        let child_id: Dtype = self.parse_shared(&child_path, &new_tname, child)?;
+                        let fk: String = self.generated_column(
+                            t_name,
+                            &foreign_key_column(&new_tname),
+                            &columns,
+                        );
+                        self.reference(t_name, &fk, &new_tname);
+                        self.relations.add(Relationship {
+                            parent_table: t_name.to_owned(),
+                            child_table: new_tname.clone(),
+                            fk_column: fk.clone(),
+                            cardinality: Cardinality::ManyToOne,
+                            json_key: k.to_string(),
+                            junction: None,
+                        });
+                        this_record.insert(fk, child_id);
+                    } else {
+                        self.relate(t_name, &new_tname, k, Cardinality::OneToOne);
+                        self.parse_object(
+                            &child_path,
+                            &new_tname,
+                            child,
+                            Some(&this_id),
+                            Some(t_name),
+                        )?;
+                    }
+                    self.leave(tokens);
                 }
                 _ => {
                     // if the type if non-nested, just insert it into the row container
-                    this_record.insert(k.to_string(), Dtype::from_value(v.to_owned()));
+                    this_record.insert(column, Self::to_dtype(path, tokens, v)?);
                 } // _ => this_table.extend_column(k.to_string(), v.to_owned()),
             }
         }
+        if self.ancestor_keys != AncestorKeys::Parent {
+            self.ancestors.pop();
+        }
         // transform and add the row container to the current table
         self.add_record(t_name.to_owned(), this_record);
         Ok(())
     }
 
+    fn insert_ancestor_keys(
+        &mut self,
+        t_name: &str,
+        record: &mut IndexMap<String, Dtype>,
+        fields: &[String],
+    ) {
+        // the direct parent is last on the stack and already gets a foreign key of its own
+        let n_ancestors: usize = self.ancestors.len().saturating_sub(1);
+        let ancestors: Vec<(String, Dtype)> = match self.ancestor_keys {
+            AncestorKeys::Parent => vec![],
+            AncestorKeys::Root => self.ancestors[..n_ancestors.min(1)].to_vec(),
+            AncestorKeys::All => self.ancestors[..n_ancestors].to_vec(),
+        };
+        for (ancestor, ancestor_id) in ancestors {
+            let fk: String = self.generated_column(t_name, &foreign_key_column(&ancestor), fields);
+            self.reference(t_name, &fk, &ancestor);
+            record.insert(fk, ancestor_id);
+        }
+    }
+
+    fn enter(&mut self, tokens: &[String]) {
+        // descends into object keys or an array index, tracked only in provenance mode
+        if self.provenance {
+            self.pointer.extend_from_slice(tokens);
+        }
+    }
+
+    fn leave(&mut self, tokens: &[String]) {
+        if self.provenance {
+            self.pointer.truncate(self.pointer.len() - tokens.len());
+        }
+    }
+
+    fn json_pointer(&self) -> String {
+        // the rfc 6901 pointer of the value being parsed, `~` and `/` escaped in every token
+        self.pointer
+            .iter()
+            .map(|token| format!("/{}", token.replace('~', "~0").replace('/', "~1")))
+            .collect()
+    }
+
+    fn explode(
+        &mut self,
+        path: &TablePath,
+        key: &str,
+        arr: &[Value],
+        p_name: &str,
+        p_id: &Dtype,
+    ) -> Result<()> {
+        // stores an array of primitives in a child table holding the parent id, the element's
+        // position and the element, the shape `DataBase::explode_list_columns` produces
+        let t_name: String = self.namer.name_for(path)?;
+        let fields: [String; 1] = [key.to_string()];
+        let fk: String = self.generated_column(&t_name, &foreign_key_column(p_name), &fields);
+        self.relate(p_name, &t_name, key, Cardinality::OneToMany);
+        self.reference(&t_name, &fk, p_name);
+        let ordinal: String = self.generated_column(&t_name, ORDINAL_COLUMN, &fields);
+        for (i, item) in arr.iter().enumerate() {
+            let element: Dtype = Self::to_dtype(path, &[], item)?;
+            let mut record: IndexMap<String, Dtype> = IndexMap::from([(fk.clone(), p_id.clone())]);
+            self.insert_ancestor_keys(&t_name, &mut record, &fields);
+            record.insert(ordinal.clone(), Dtype::UInt(i as u64));
+            record.insert(key.to_string(), element);
+            self.add_record(t_name.clone(), record);
+        }
+        Ok(())
+    }
+
+    fn relate(&mut self, parent: &str, child: &str, key: &str, cardinality: Cardinality) {
+        // records the link between a table and a child table found under `key`
+        self.relations.add(Relationship {
+            parent_table: parent.to_string(),
+            child_table: child.to_string(),
+            fk_column: foreign_key_column(parent),
+            cardinality,
+            json_key: key.to_string(),
+            junction: None,
+        });
+    }
+
+    fn generated_column(&mut self, t_name: &str, name: &str, fields: &[String]) -> String {
+        self.tables
+            .entry(t_name.to_string())
+            .or_insert_with(TableData::new)
+            .generated_column(name, name, fields)
+    }
+
+    fn reference(&mut self, t_name: &str, fk: &str, references: &str) {
+        // declares that `fk` of `t_name` holds ids of the `references` table
+        self.tables
+            .entry(t_name.to_string())
+            .or_insert_with(TableData::new)
+            .foreign_keys
+            .entry(fk.to_string())
+            .or_insert_with(|| references.to_string());
+    }
+
+    fn parse_junction(
+        &mut self,
+        path: &TablePath,
+        t_name: &String,
+        arr: &[Value],
+        p_name: &String,
+        p_id: &Dtype,
+        key: &str,
+    ) -> Result<()> {
+        // stores every object of the array once and links it to the parent row through a
+        // junction table holding the parent id, the child id and the item's array position
+        let junction: String = self.namer.junction_for(path, t_name);
+        let parent_fk: String = foreign_key_column(p_name);
+        let child_fk: String = foreign_key_column(t_name);
+        self.relations.add(Relationship {
+            parent_table: p_name.to_owned(),
+            child_table: t_name.to_owned(),
+            fk_column: parent_fk.clone(),
+            cardinality: Cardinality::ManyToMany,
+            json_key: key.to_string(),
+            junction: Some(junction.clone()),
+        });
+        for (i, item) in arr.iter().enumerate() {
+            let obj: &Map<String, Value> =
+                item.as_object().ok_or_else(|| NormError::ExpectedObject {
+                    path: format!("{}[{}]", path, i),
+                    found: json_type_name(item),
+                })?;
+            let index: [String; 1] = [i.to_string()];
+            self.enter(&index);
+            let child_id: Dtype = self.parse_shared(path, t_name, obj)?;
+            self.leave(&index);
+            self.reference(&junction, &parent_fk, p_name);
+            self.reference(&junction, &child_fk, t_name);
+            let ordinal: String = self.generated_column(&junction, ORDINAL_COLUMN, &[]);
+            let mut record: IndexMap<String, Dtype> = IndexMap::from([
+                (parent_fk.clone(), p_id.clone()),
+                (child_fk.clone(), child_id),
+            ]);
+            self.insert_ancestor_keys(&junction, &mut record, &[]);
+            record.insert(ordinal, Dtype::UInt(i as u64));
+            self.add_record(junction.clone(), record);
+        }
+        Ok(())
+    }
+
+    fn to_dtype(path: &TablePath, tokens: &[String], value: &Value) -> Result<Dtype> {
+        // converts a non-nested value, attaching the json path of the offending key on failure
+        Dtype::from_value(value.to_owned())
+            .map_err(|e| e.at_path(&path.descendant(tokens).to_string()))
+    }
+
+    fn reshapes_rows(&self) -> bool {
+        // whether any setting can inline, drop or convert fields, rows are copied key by key
+        // without looking up json paths otherwise
+        self.flatten_depth > 0 || !self.flatten_paths.is_empty() || !self.rules.is_empty()
+    }
+
+    fn field_action(&self, path: &TablePath, tokens: &[String]) -> Option<Action> {
+        if self.rules.is_empty() {
+            return None;
+        }
+        self.rules.action(&path.descendant(tokens))
+    }
+
+    fn inlines(&self, path: &TablePath, action: Option<Action>, level: usize) -> bool {
+        // whether an object `level` objects below its row is flattened into the row, a rule
+        // for the path decides over the flatten depth and paths
+        match action {
+            Some(action) => action == Action::Flatten,
+            None => level < self.flatten_depth || self.flatten_paths.contains(&path.to_string()),
+        }
+    }
+
+    fn row_fields<'v>(&self, path: &TablePath, obj: &'v Map<String, Value>) -> Vec<Field<'v>> {
+        if !self.reshapes_rows() {
+            return obj
+                .iter()
+                .map(|(k, v)| (Cow::Borrowed(std::slice::from_ref(k)), v))
+                .collect();
+        }
+        let mut fields: Vec<Field<'v>> = Vec::new();
+        self.flatten_fields(path, &[], obj, 0, &mut fields);
+        fields
+    }
+
+    fn flatten_fields<'v>(
+        &self,
+        path: &TablePath,
+        prefix: &[String],
+        obj: &'v Map<String, Value>,
+        level: usize,
+        fields: &mut Vec<Field<'v>>,
+    ) {
+        // the fields of a row with every inlined object replaced by its own fields
+        for (k, v) in obj {
+            let field_path: TablePath = path.child(k);
+            let mut tokens: Vec<String> = prefix.to_vec();
+            tokens.push(k.clone());
+            let action: Option<Action> = self.rules.action(&field_path);
+            match v {
+                _ if action == Some(Action::Drop) => {}
+                Value::Object(child) if self.inlines(&field_path, action, level) => {
+                    self.flatten_fields(&field_path, &tokens, child, level + 1, fields)
+                }
+                _ => fields.push((Cow::Owned(tokens), v)),
+            }
+        }
+    }
+
     pub(crate) fn parse_object_array(
         &mut self,
+        path: &TablePath,
         t_name: &String,
-        arr: &Vec<Value>,
+        arr: &[Value],
         p_name: Option<&String>,
-        row_id: Option<&String>,
+        row_id: Option<&Dtype>,
     ) -> Result<()> {
-        for obj in arr {
+        for (i, item) in arr.iter().enumerate() {
             // parse each object in the array
-            self.parse_object(t_name, obj.as_object().unwrap(), row_id, p_name)?;
+            let obj: &Map<String, Value> =
+                item.as_object().ok_or_else(|| NormError::ExpectedObject {
+                    path: format!("{}[{}]", path, i),
+                    found: json_type_name(item),
+                })?;
+            if self.ordinals && p_name.is_some() {
+                self.pending_ordinal = Some(i);
+            }
+            let index: [String; 1] = [i.to_string()];
+            self.enter(&index);
+            self.parse_object(path, t_name, obj, row_id, p_name)?;
+            self.leave(&index);
         }
         Ok(())
     }
 
+    fn find_shared_arrays<'v>(
+        &mut self,
+        documents: impl IntoIterator<Item = &'v Value>,
+        root_name: &str,
+    ) -> Result<()> {
+        // walks the documents once before normalizing them, recording every array path whose
+        // objects occur under more than one parent object
+        if self.tables.values().any(|t| t.n_rows() > 0) {
+            // paths found now would change the shape of tables that already hold rows
+            return Err(NormError::JunctionDetection);
+        }
+        let mut owners: HashMap<(String, String), usize> = HashMap::new();
+        let mut n_objects: usize = 0;
+        let root_path: TablePath = TablePath::root(root_name);
+        for document in documents {
+            match document {
+                Value::Array(arr) => {
+                    for item in arr {
+                        self.visit_shared(&root_path, item, &mut owners, &mut n_objects);
+                    }
+                }
+                other => self.visit_shared(&root_path, other, &mut owners, &mut n_objects),
+            }
+        }
+        Ok(())
+    }
+
+    fn visit_shared(
+        &mut self,
+        path: &TablePath,
+        value: &Value,
+        owners: &mut HashMap<(String, String), usize>,
+        n_objects: &mut usize,
+    ) {
+        let Value::Object(obj) = value else {
+            return;
+        };
+        *n_objects += 1;
+        let this_object: usize = *n_objects;
+        for (k, v) in obj {
+            let child_path: TablePath = path.child(k);
+            match v {
+                Value::Array(arr) if !arr.is_empty() && arr.iter().all(Value::is_object) => {
+                    let display: String = child_path.to_string();
+                    for item in arr {
+                        if let Value::Object(child) = item {
+                            let (_, identity) = self.ids.shared_id(child);
+                            let owner: usize = *owners
+                                .entry((display.clone(), identity))
+                                .or_insert(this_object);
+                            if owner != this_object {
+                                self.junction_paths.insert(display.clone());
+                            }
+                        }
+                        self.visit_shared(&child_path, item, owners, n_objects);
+                    }
+                }
+                Value::Object(_) => self.visit_shared(&child_path, v, owners, n_objects),
+                _ => {}
+            }
+        }
+    }
+
     pub(crate) fn process_root(&mut self, root_value: Value, root_name: String) -> Result<()> {
+        let root_path: TablePath = TablePath::root(&root_name);
+        let root_name: String = self.namer.name_for(&root_path)?;
+        // several values are rebuilt as an array of records, one per value
+        if self.tables.get(&root_name).is_some_and(|t| t.n_rows() > 0) {
+            self.root_is_array = true;
+        }
         match root_value {
             Value::Object(root_obj) => {
-                self.parse_object(&root_name, &root_obj, None, None)?;
+                self.parse_object(&root_path, &root_name, &root_obj, None, None)?;
             }
             Value::Array(arr) => {
-                self.parse_object_array(&root_name, &arr, None, None)?;
+                self.root_is_array = true;
+                self.parse_object_array(&root_path, &root_name, &arr, None, None)?;
             }
-            _ => {
-                panic!("Neither Object nor Array found at root of JSON");
+            other => {
+                return Err(NormError::InvalidRoot {
+                    path: root_path.to_string(),
+                    found: json_type_name(&other),
+                });
             }
         }
         Ok(())
@@ -0,0 +1,198 @@
+use crate::{
+    database_builder::DataBase,
+    ddl::{Dialect, quote_ident},
+    error::{NormError, Result},
+};
+use polars::prelude::*;
+use std::{fs, path::Path};
+
+/// The two `COPY` input formats postgres accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CopyFormat {
+    // tab separated, `\N` for null, backslash escapes
+    #[default]
+    Text,
+    // comma separated, empty unquoted field for null, double-quote escapes
+    Csv,
+}
+
+impl CopyFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            // pg_dump's directory format uses the same extension for text copy data
+            Self::Text => "dat",
+            Self::Csv => "csv",
+        }
+    }
+
+    fn options(&self) -> &'static str {
+        match self {
+            Self::Text => "",
+            Self::Csv => " WITH (FORMAT csv)",
+        }
+    }
+
+    fn separator(&self) -> &'static str {
+        match self {
+            Self::Text => "\t",
+            Self::Csv => ",",
+        }
+    }
+}
+
+fn escape_text(value: &str) -> String {
+    // escapes the characters the text format uses as delimiters
+    let mut escaped: String = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            other => escaped.push(other),
+        }
+    }
+    escaped
+}
+
+fn escape_csv(value: &str) -> String {
+    // quotes every non-null value so an empty string is never read back as null
+    format!("\"{}\"", value.replace('"', "\"\""))
+}
+
+fn array_element(value: AnyValue) -> String {
+    match value {
+        AnyValue::Null => "NULL".to_string(),
+        AnyValue::Boolean(b) => if b { "t" } else { "f" }.to_string(),
+        AnyValue::String(s) => quote_array_string(s),
+        AnyValue::StringOwned(s) => quote_array_string(&s),
+        AnyValue::List(s) => array_literal(&s),
+        // polars rounds floats for display, rust prints the shortest exact form
+        AnyValue::Float32(f) => f.to_string(),
+        AnyValue::Float64(f) => f.to_string(),
+        other => other.to_string(),
+    }
+}
+
+fn quote_array_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn array_literal(values: &Series) -> String {
+    // encodes a list cell as a postgres array literal, e.g. `{"a","b",NULL}`
+    let elements: Vec<String> = values.rechunk().iter().map(array_element).collect();
+    format!("{{{}}}", elements.join(","))
+}
+
+fn copy_field(value: AnyValue, format: CopyFormat) -> String {
+    let raw: String = match value {
+        AnyValue::Null => {
+            return match format {
+                CopyFormat::Text => "\\N".to_string(),
+                CopyFormat::Csv => String::new(),
+            };
+        }
+        AnyValue::Boolean(b) => if b { "t" } else { "f" }.to_string(),
+        AnyValue::String(s) => s.to_string(),
+        AnyValue::StringOwned(s) => s.to_string(),
+        AnyValue::List(s) => array_literal(&s),
+        // polars rounds floats for display, rust prints the shortest exact form
+        AnyValue::Float32(f) => f.to_string(),
+        AnyValue::Float64(f) => f.to_string(),
+        other => other.to_string(),
+    };
+    match format {
+        CopyFormat::Text => escape_text(&raw),
+        CopyFormat::Csv => escape_csv(&raw),
+    }
+}
+
+fn copy_rows(df: &DataFrame, format: CopyFormat) -> String {
+    // renders every row of a dataframe as `COPY ... FROM STDIN` input
+    let columns: Vec<Vec<String>> = df
+        .get_columns()
+        .iter()
+        .map(|c| {
+            c.as_materialized_series()
+                .rechunk()
+                .iter()
+                .map(|v| copy_field(v, format))
+                .collect()
+        })
+        .collect();
+    let mut out: String = String::new();
+    for row in 0..df.height() {
+        let fields: Vec<&str> = columns.iter().map(|c| c[row].as_str()).collect();
+        out.push_str(&fields.join(format.separator()));
+        out.push('\n');
+    }
+    out
+}
+
+fn column_list(df: &DataFrame) -> String {
+    df.get_column_names()
+        .iter()
+        .map(|c| quote_ident(c))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+impl DataBase {
+    pub fn copy_statement(&self, table_name: &str, format: CopyFormat) -> Result<String> {
+        // the statement a driver sends before streaming `copy_data` to the server
+        let df: &DataFrame = self
+            .get(table_name)
+            .ok_or_else(|| NormError::UnknownTable(table_name.to_string()))?;
+        Ok(format!(
+            "COPY {} ({}) FROM STDIN{};",
+            quote_ident(table_name),
+            column_list(df),
+            format.options()
+        ))
+    }
+
+    pub fn copy_data(&self, table_name: &str, format: CopyFormat) -> Result<String> {
+        let df: &DataFrame = self
+            .get(table_name)
+            .ok_or_else(|| NormError::UnknownTable(table_name.to_string()))?;
+        Ok(copy_rows(df, format))
+    }
+
+    pub fn write_pg_copy(&self, dir: impl AsRef<Path>, format: CopyFormat) -> Result<()> {
+        // writes one `COPY` input file per table, `schema.sql` with the postgres ddl, and
+        // `load.sql`, a psql script that creates the tables and loads them parent-first
+        let dir: &Path = dir.as_ref();
+        fs::create_dir_all(dir).map_err(|e| NormError::io(dir, e))?;
+
+        let schema_path = dir.join("schema.sql");
+        fs::write(&schema_path, self.to_ddl(Dialect::Postgres)?)
+            .map_err(|e| NormError::io(&schema_path, e))?;
+
+        let mut script: Vec<String> = vec![
+            "\\set ON_ERROR_STOP on".to_string(),
+            "BEGIN;".to_string(),
+            "\\ir schema.sql".to_string(),
+        ];
+        for name in self.relations().parent_first(self.table_names()) {
+            let df: &DataFrame = self
+                .get(name)
+                .ok_or_else(|| NormError::UnknownTable(name.to_string()))?;
+            let file: String = format!("{}.{}", name, format.extension());
+            let path = dir.join(&file);
+            fs::write(&path, copy_rows(df, format)).map_err(|e| NormError::io(&path, e))?;
+            // psql's `\copy` streams the file to the server through `COPY ... FROM STDIN`,
+            // its path is resolved against psql's working directory, i.e. run from `dir`
+            script.push(format!(
+                "\\copy {} ({}) FROM '{}'{}",
+                quote_ident(name),
+                column_list(df),
+                file.replace('\'', "''"),
+                format.options()
+            ));
+        }
+        script.push("COMMIT;".to_string());
+
+        let load_path = dir.join("load.sql");
+        fs::write(&load_path, script.join("\n") + "\n").map_err(|e| NormError::io(&load_path, e))
+    }
+}
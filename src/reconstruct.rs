@@ -0,0 +1,253 @@
+use crate::{
+    database_builder::DataBase,
+    error::{NormError, Result},
+    export::any_value_to_json,
+    normalizer::{ID_COLUMN, METADATA_COLUMNS, ORDINAL_COLUMN, foreign_key_column},
+    relations::{Cardinality, Relationship},
+};
+use indexmap::IndexMap;
+use polars::prelude::*;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+/// The cells of one table converted back to json, indexed by key values.
+struct JsonTable {
+    // each row's primary key, absent for junction tables and tables exploded from lists
+    ids: Option<Vec<Value>>,
+    // the row holding each encoded primary key value
+    by_id: HashMap<String, usize>,
+    // every column that is not a key, an ordinal or provenance, in table order
+    columns: Vec<(String, Vec<Value>)>,
+    // the values of every foreign key column
+    foreign_keys: IndexMap<String, Vec<Value>>,
+    // row indices in table order per foreign key column, keyed by the encoded referenced id
+    by_reference: IndexMap<String, IndexMap<String, Vec<usize>>>,
+}
+
+impl JsonTable {
+    fn rows_referencing(&self, fk: &str, id: &Value) -> &[usize] {
+        self.by_reference
+            .get(fk)
+            .and_then(|rows| rows.get(&id.to_string()))
+            .map_or(&[], Vec::as_slice)
+    }
+}
+
+fn column_json(column: &Column) -> Vec<Value> {
+    column
+        .as_materialized_series()
+        .rechunk()
+        .iter()
+        .map(any_value_to_json)
+        .collect()
+}
+
+fn foreign_key_columns(db: &DataBase, name: &str) -> Vec<String> {
+    match db.schema(name) {
+        Some(schema) => schema
+            .foreign_keys
+            .iter()
+            .map(|fk| fk.column.clone())
+            .collect(),
+        None => db
+            .relations()
+            .iter()
+            .filter(|r| r.referencing_table() == name)
+            .map(|r| r.fk_column.clone())
+            .collect(),
+    }
+}
+
+fn json_table(db: &DataBase, name: &str, df: &DataFrame) -> Result<JsonTable> {
+    let primary_key: Option<&str> = match db.schema(name) {
+        Some(schema) => schema.primary_key.as_deref(),
+        None => df.column(ID_COLUMN).ok().map(|_| ID_COLUMN),
+    };
+    // a promoted natural key is a json field, only generated ids are dropped
+    let generated_key: Option<&str> =
+        primary_key.filter(|pk| !db.schema(name).is_some_and(|s| s.is_candidate_key(pk)));
+    let fk_columns: Vec<String> = foreign_key_columns(db, name);
+    // ordinal and provenance columns are named in the schema, their default names otherwise
+    let is_metadata = |column: &str| match db.schema(name) {
+        Some(schema) => schema.is_metadata(column),
+        None => METADATA_COLUMNS.contains(&column),
+    };
+    let ordinal: Option<&str> = match db.schema(name) {
+        Some(schema) => schema.ordinal.as_deref(),
+        None => Some(ORDINAL_COLUMN),
+    };
+    let is_key = |column: &str| {
+        Some(column) == generated_key
+            || is_metadata(column)
+            || fk_columns.iter().any(|fk| fk == column)
+    };
+
+    let ids: Option<Vec<Value>> = primary_key
+        .map(|pk| df.column(pk).map(column_json))
+        .transpose()
+        .map_err(|e| NormError::export(name, e))?;
+    let by_id: HashMap<String, usize> = ids
+        .iter()
+        .flatten()
+        .enumerate()
+        .map(|(row, id)| (id.to_string(), row))
+        .collect();
+    // rows are grouped in array order, which is table order unless an ordinal was recorded
+    let mut order: Vec<usize> = (0..df.height()).collect();
+    if let Some(Ok(ordinals)) = ordinal.map(|ordinal| df.column(ordinal)) {
+        let ordinals: Vec<Value> = column_json(ordinals);
+        order.sort_by_key(|&row| ordinals[row].as_u64());
+    }
+    let mut foreign_keys: IndexMap<String, Vec<Value>> = IndexMap::new();
+    let mut by_reference: IndexMap<String, IndexMap<String, Vec<usize>>> = IndexMap::new();
+    for fk in fk_columns.iter() {
+        let values: Vec<Value> = df
+            .column(fk)
+            .map(column_json)
+            .map_err(|e| NormError::export(name, e))?;
+        let rows: &mut IndexMap<String, Vec<usize>> = by_reference.entry(fk.clone()).or_default();
+        for &row in &order {
+            rows.entry(values[row].to_string()).or_default().push(row);
+        }
+        foreign_keys.insert(fk.clone(), values);
+    }
+    let columns: Vec<(String, Vec<Value>)> = df
+        .get_columns()
+        .iter()
+        .filter(|c| !is_key(c.name()))
+        .map(|c| (c.name().to_string(), column_json(c)))
+        .collect();
+    Ok(JsonTable {
+        ids,
+        by_id,
+        columns,
+        foreign_keys,
+        by_reference,
+    })
+}
+
+impl DataBase {
+    pub fn to_json(&self) -> Result<Value> {
+        // rebuilds the nested document: key columns are dropped, one-to-one children are
+        // nested as objects and one-to-many children as arrays in row order, null cells are
+        // left out since a null and a missing key are stored the same way. objects that were
+        // flattened into prefixed columns stay flat
+        let tables: IndexMap<&str, JsonTable> = self
+            .iter()
+            .map(|(name, df)| Ok((name.as_str(), json_table(self, name, df)?)))
+            .collect::<Result<IndexMap<&str, JsonTable>>>()?;
+        let records: Vec<Value> = match self.relations().roots(self.table_names()).first() {
+            Some(root) => {
+                let n_rows: usize = self.get(root).map_or(0, DataFrame::height);
+                (0..n_rows)
+                    .map(|row| self.row_json(&tables, root, row))
+                    .collect::<Result<Vec<Value>>>()?
+            }
+            None => vec![],
+        };
+        if self.root_is_array {
+            Ok(Value::Array(records))
+        } else {
+            Ok(records.into_iter().next().unwrap_or(Value::Null))
+        }
+    }
+
+    fn row_json(
+        &self,
+        tables: &IndexMap<&str, JsonTable>,
+        table: &str,
+        row: usize,
+    ) -> Result<Value> {
+        let data: &JsonTable = &tables[table];
+        let mut object: Map<String, Value> = Map::new();
+        for (column, values) in &data.columns {
+            if !values[row].is_null() {
+                object.insert(column.clone(), values[row].clone());
+            }
+        }
+        for relation in self.relations().children_of(table) {
+            let Some(child) = tables.get(relation.child_table.as_str()) else {
+                continue;
+            };
+            let rows: Vec<usize> = Self::child_rows(tables, relation, data, row)?;
+            if rows.is_empty() {
+                continue;
+            }
+            let mut items: Vec<Value> = rows
+                .into_iter()
+                .map(|i| self.element_json(tables, &relation.child_table, child, i))
+                .collect::<Result<Vec<Value>>>()?;
+            let value: Value = match relation.cardinality {
+                Cardinality::OneToOne | Cardinality::ManyToOne => items.swap_remove(0),
+                Cardinality::OneToMany | Cardinality::ManyToMany => Value::Array(items),
+            };
+            object.insert(relation.json_key.clone(), value);
+        }
+        Ok(Value::Object(object))
+    }
+
+    fn child_rows(
+        tables: &IndexMap<&str, JsonTable>,
+        relation: &Relationship,
+        parent: &JsonTable,
+        row: usize,
+    ) -> Result<Vec<usize>> {
+        // the rows of the child table nested under one parent row, in order
+        let parent_id = || {
+            parent.ids.as_ref().map(|ids| &ids[row]).ok_or_else(|| {
+                NormError::export(
+                    &relation.parent_table,
+                    "no primary key column to attach child rows to",
+                )
+            })
+        };
+        let child: &JsonTable = &tables[relation.child_table.as_str()];
+        Ok(match relation.cardinality {
+            Cardinality::OneToOne | Cardinality::OneToMany => child
+                .rows_referencing(&relation.fk_column, parent_id()?)
+                .to_vec(),
+            Cardinality::ManyToOne => parent
+                .foreign_keys
+                .get(&relation.fk_column)
+                .and_then(|ids| child.by_id.get(&ids[row].to_string()))
+                .into_iter()
+                .copied()
+                .collect(),
+            Cardinality::ManyToMany => {
+                let Some(junction) = relation.junction.as_deref().and_then(|j| tables.get(j))
+                else {
+                    return Ok(vec![]);
+                };
+                let child_ids: &Vec<Value> = junction
+                    .foreign_keys
+                    .get(&foreign_key_column(&relation.child_table))
+                    .ok_or_else(|| {
+                        NormError::export(&relation.child_table, "junction has no child column")
+                    })?;
+                junction
+                    .rows_referencing(&relation.fk_column, parent_id()?)
+                    .iter()
+                    .filter_map(|&link| child.by_id.get(&child_ids[link].to_string()))
+                    .copied()
+                    .collect()
+            }
+        })
+    }
+
+    fn element_json(
+        &self,
+        tables: &IndexMap<&str, JsonTable>,
+        table: &str,
+        child: &JsonTable,
+        row: usize,
+    ) -> Result<Value> {
+        if child.ids.is_some() {
+            return self.row_json(tables, table, row);
+        }
+        // a table exploded from a list column holds one list element per row
+        Ok(child
+            .columns
+            .first()
+            .map_or(Value::Null, |(_, values)| values[row].clone()))
+    }
+}
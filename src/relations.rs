@@ -0,0 +1,185 @@
+/// How many child rows a single parent row can own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cardinality {
+    // the child came from a json object nested under the parent
+    OneToOne,
+    // the child came from an array of objects nested under the parent
+    OneToMany,
+    // a deduplicated object shared by many parents, the parent holds the child's id
+    ManyToOne,
+    // a deduplicated array of objects, a junction table pairs parent and child ids
+    ManyToMany,
+}
+
+/// A parent/child link between two generated tables.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Relationship {
+    pub parent_table: String,
+    pub child_table: String,
+    // the column holding the referenced id: the child's column holding the parent id, the
+    // parent's column holding the child id for many-to-one, the junction's column holding the
+    // parent id for many-to-many
+    pub fk_column: String,
+    pub cardinality: Cardinality,
+    // the json key of the parent object the child was found under
+    pub json_key: String,
+    // the table pairing parent and child ids of a many-to-many relationship
+    pub junction: Option<String>,
+}
+
+impl Relationship {
+    pub fn referencing_table(&self) -> &str {
+        // the table `fk_column` belongs to
+        match self.cardinality {
+            Cardinality::OneToOne | Cardinality::OneToMany => &self.child_table,
+            Cardinality::ManyToOne => &self.parent_table,
+            Cardinality::ManyToMany => self.junction.as_deref().unwrap_or(&self.child_table),
+        }
+    }
+
+    fn dependencies(&self) -> Vec<(&str, &str)> {
+        // (table, table it references), a referenced table has to be created first
+        match self.cardinality {
+            Cardinality::OneToOne | Cardinality::OneToMany => {
+                vec![(&self.child_table, &self.parent_table)]
+            }
+            Cardinality::ManyToOne => vec![(&self.parent_table, &self.child_table)],
+            Cardinality::ManyToMany => {
+                let junction: &str = self.referencing_table();
+                vec![
+                    (junction, &self.parent_table),
+                    (junction, &self.child_table),
+                ]
+            }
+        }
+    }
+}
+
+/// Every relationship between the tables of a normalized document, in discovery order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RelationGraph {
+    pub(crate) relations: Vec<Relationship>,
+}
+
+impl RelationGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn add(&mut self, relation: Relationship) {
+        // a path that holds an object in one record and an array in another is one-to-many
+        match self.relations.iter_mut().find(|r| {
+            r.parent_table == relation.parent_table && r.child_table == relation.child_table
+        }) {
+            Some(existing) => {
+                if existing.cardinality == Cardinality::OneToOne
+                    && relation.cardinality == Cardinality::OneToMany
+                {
+                    existing.cardinality = Cardinality::OneToMany;
+                }
+            }
+            None => self.relations.push(relation),
+        }
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Relationship> {
+        self.relations.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.relations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.relations.is_empty()
+    }
+
+    pub fn children_of<'a>(&'a self, table: &'a str) -> impl Iterator<Item = &'a Relationship> {
+        self.relations
+            .iter()
+            .filter(move |r| r.parent_table == table)
+    }
+
+    pub fn parent_of(&self, table: &str) -> Option<&Relationship> {
+        self.relations.iter().find(|r| r.child_table == table)
+    }
+
+    pub fn roots<'a>(&'a self, tables: impl IntoIterator<Item = &'a str>) -> Vec<&'a str> {
+        // tables that are neither the child nor the junction of any relationship
+        tables
+            .into_iter()
+            .filter(|t| {
+                self.parent_of(t).is_none()
+                    && !self
+                        .relations
+                        .iter()
+                        .any(|r| r.junction.as_deref() == Some(*t))
+            })
+            .collect()
+    }
+
+    pub fn parent_first<'a>(&self, tables: impl IntoIterator<Item = &'a str>) -> Vec<&'a str> {
+        // orders tables so every referenced table precedes the tables referencing it, i.e.
+        // parents before children, keeping the given order otherwise
+        let dependencies: Vec<(&str, &str)> = self
+            .relations
+            .iter()
+            .flat_map(Relationship::dependencies)
+            .collect();
+        let mut pending: Vec<&str> = tables.into_iter().collect();
+        let mut ordered: Vec<&str> = Vec::with_capacity(pending.len());
+        while !pending.is_empty() {
+            let ready: Option<usize> = pending.iter().position(|t| {
+                dependencies.iter().all(|(table, referenced)| {
+                    table != t
+                        || referenced == t
+                        || ordered.contains(referenced)
+                        || !pending.contains(referenced)
+                })
+            });
+            // a cycle cannot come out of a json tree, but never loop forever on edited graphs
+            ordered.push(pending.remove(ready.unwrap_or(0)));
+        }
+        ordered
+    }
+
+    pub(crate) fn rename_table(&mut self, table: &str, new_name: &str) {
+        for r in self.relations.iter_mut() {
+            if r.parent_table == table {
+                r.parent_table = new_name.to_string();
+            }
+            if r.child_table == table {
+                r.child_table = new_name.to_string();
+            }
+            if r.junction.as_deref() == Some(table) {
+                r.junction = Some(new_name.to_string());
+            }
+        }
+    }
+
+    pub(crate) fn rename_fk_column(&mut self, table: &str, column: &str, new_name: &str) {
+        // follows a foreign key column that was renamed to keep a json key of the same name
+        for r in self.relations.iter_mut() {
+            if r.referencing_table() == table && r.fk_column == column {
+                r.fk_column = new_name.to_string();
+            }
+        }
+    }
+
+    pub(crate) fn remove_table(&mut self, table: &str) {
+        self.relations.retain(|r| {
+            r.parent_table != table
+                && r.child_table != table
+                && r.junction.as_deref() != Some(table)
+        });
+    }
+}
+
+impl<'a> IntoIterator for &'a RelationGraph {
+    type Item = &'a Relationship;
+    type IntoIter = std::slice::Iter<'a, Relationship>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.relations.iter()
+    }
+}
@@ -0,0 +1,177 @@
+use crate::{
+    error::{NormError, Result},
+    naming::TablePath,
+};
+use indexmap::IndexMap;
+use serde_json::{Map, Value};
+use std::{fs, path::Path};
+
+/// What the `Normifier` does with the value found at a json path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// inlines an object into its row as prefixed columns, which `DataBase::to_json` does
+    /// not nest back
+    Flatten,
+    /// keeps an object as its own table, even within the flatten depth
+    Table,
+    /// stores an object or array as a json string column
+    JsonString,
+    /// stores an array of primitives as a child table with one row per element
+    Explode,
+    /// leaves the value out
+    Drop,
+}
+
+impl Action {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Flatten => "flatten",
+            Self::Table => "table",
+            Self::JsonString => "json",
+            Self::Explode => "explode",
+            Self::Drop => "drop",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "flatten" => Some(Self::Flatten),
+            "table" => Some(Self::Table),
+            "json" => Some(Self::JsonString),
+            "explode" => Some(Self::Explode),
+            "drop" => Some(Self::Drop),
+            _ => None,
+        }
+    }
+}
+
+/// The rule for the values at one json path.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PathRule {
+    pub action: Option<Action>,
+    /// the name of the table created for this path
+    pub rename: Option<String>,
+    /// the field promoted to primary key of the table created for this path
+    pub primary_key: Option<String>,
+}
+
+/// Per-path normalization rules, keyed by the display form of a json path, e.g. `$.address`.
+///
+/// The configuration holds a `rules` table with one entry per path, in json
+/// `{"rules": {"$.address": {"action": "flatten"}}}` or in toml:
+///
+/// ```toml
+/// [rules."$.address"]
+/// action = "flatten"
+///
+/// [rules."$.lobbyists"]
+/// rename = "lobbyists"
+/// primary_key = "lobbyist_id"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Rules {
+    paths: IndexMap<String, PathRule>,
+}
+
+fn optional_str(rule: &Map<String, Value>, path: &str, key: &str) -> Result<Option<String>> {
+    match rule.get(key) {
+        None => Ok(None),
+        Some(Value::String(s)) => Ok(Some(s.clone())),
+        Some(_) => Err(NormError::Rules(format!(
+            "`{}`: field `{}` is not a string",
+            path, key
+        ))),
+    }
+}
+
+impl Rules {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_rule(mut self, path: &str, rule: PathRule) -> Self {
+        self.paths.insert(path.to_string(), rule);
+        self
+    }
+
+    pub fn get(&self, path: &str) -> Option<&PathRule> {
+        self.paths.get(path)
+    }
+
+    pub fn iter(&self) -> indexmap::map::Iter<'_, String, PathRule> {
+        self.paths.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    pub(crate) fn action(&self, path: &TablePath) -> Option<Action> {
+        if self.paths.is_empty() {
+            return None;
+        }
+        self.paths
+            .get(&path.to_string())
+            .and_then(|rule| rule.action)
+    }
+
+    pub fn from_json(content: &str) -> Result<Self> {
+        Self::from_value(&serde_json::from_str(content)?)
+    }
+
+    pub fn from_toml(content: &str) -> Result<Self> {
+        let table: toml::Table =
+            toml::from_str(content).map_err(|e| NormError::Rules(e.message().to_string()))?;
+        Self::from_value(&serde_json::to_value(table)?)
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        // reads a `.toml` file as toml and any other file as json
+        let path: &Path = path.as_ref();
+        let content: String = fs::read_to_string(path).map_err(|e| NormError::io(path, e))?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => Self::from_toml(&content),
+            _ => Self::from_json(&content),
+        }
+    }
+
+    fn from_value(config: &Value) -> Result<Self> {
+        let Some(rules) = config.get("rules") else {
+            return Ok(Self::new());
+        };
+        let rules: &Map<String, Value> = rules
+            .as_object()
+            .ok_or_else(|| NormError::Rules("`rules` is not a table".to_string()))?;
+        let mut parsed: Rules = Rules::new();
+        for (path, rule) in rules {
+            let rule: &Map<String, Value> = rule
+                .as_object()
+                .ok_or_else(|| NormError::Rules(format!("`{}` is not a table", path)))?;
+            if let Some(key) = rule
+                .keys()
+                .find(|k| !["action", "rename", "primary_key"].contains(&k.as_str()))
+            {
+                return Err(NormError::Rules(format!(
+                    "`{}`: unknown field `{}`",
+                    path, key
+                )));
+            }
+            let action: Option<Action> = optional_str(rule, path, "action")?
+                .map(|a| {
+                    Action::parse(&a).ok_or_else(|| {
+                        NormError::Rules(format!("`{}`: unknown action `{}`", path, a))
+                    })
+                })
+                .transpose()?;
+            parsed.paths.insert(
+                path.clone(),
+                PathRule {
+                    action,
+                    rename: optional_str(rule, path, "rename")?,
+                    primary_key: optional_str(rule, path, "primary_key")?,
+                },
+            );
+        }
+        Ok(parsed)
+    }
+}
@@ -0,0 +1,70 @@
+use polars::prelude::{DataType, Schema};
+
+/// A column holding the primary key of another table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForeignKey {
+    pub column: String,
+    pub references: String,
+    pub referenced_column: String,
+}
+
+/// The polars schema of one table plus the keys and coercions normify applied to it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableSchema {
+    pub schema: Schema,
+    pub primary_key: Option<String>,
+    pub foreign_keys: Vec<ForeignKey>,
+    // columns whose mixed values were coerced to strings by `stringify_collection`
+    pub stringified: Vec<String>,
+    // columns that contain at least one null value
+    pub nullable: Vec<String>,
+    // json fields whose values are unique and never null, i.e. possible natural keys
+    pub candidate_keys: Vec<String>,
+    // ordinal and provenance columns added by the normifier, they hold no json values
+    pub metadata: Vec<String>,
+    // the metadata column holding each row's position in its json array
+    pub ordinal: Option<String>,
+}
+
+impl TableSchema {
+    pub fn new(schema: Schema) -> Self {
+        Self {
+            schema,
+            primary_key: None,
+            foreign_keys: Vec::new(),
+            stringified: Vec::new(),
+            nullable: Vec::new(),
+            candidate_keys: Vec::new(),
+            metadata: Vec::new(),
+            ordinal: None,
+        }
+    }
+
+    pub fn dtype(&self, column: &str) -> Option<&DataType> {
+        self.schema.get(column)
+    }
+
+    pub fn is_primary_key(&self, column: &str) -> bool {
+        self.primary_key.as_deref() == Some(column)
+    }
+
+    pub fn foreign_key(&self, column: &str) -> Option<&ForeignKey> {
+        self.foreign_keys.iter().find(|fk| fk.column == column)
+    }
+
+    pub fn is_stringified(&self, column: &str) -> bool {
+        self.stringified.iter().any(|c| c == column)
+    }
+
+    pub fn is_nullable(&self, column: &str) -> bool {
+        self.nullable.iter().any(|c| c == column)
+    }
+
+    pub fn is_candidate_key(&self, column: &str) -> bool {
+        self.candidate_keys.iter().any(|c| c == column)
+    }
+
+    pub fn is_metadata(&self, column: &str) -> bool {
+        self.metadata.iter().any(|c| c == column)
+    }
+}
@@ -0,0 +1,97 @@
+use crate::{
+    database_builder::DataBase,
+    ddl::{Dialect, create_indexes, create_table, quote_ident},
+    error::{NormError, Result},
+    schema::TableSchema,
+};
+use polars::prelude::*;
+use rusqlite::{Connection, Transaction, params_from_iter, types::Value as SqlValue};
+use std::path::Path;
+
+fn to_sql_value(value: AnyValue) -> SqlValue {
+    match value {
+        AnyValue::Null => SqlValue::Null,
+        AnyValue::Boolean(b) => SqlValue::Integer(b as i64),
+        AnyValue::String(s) => SqlValue::Text(s.to_string()),
+        AnyValue::StringOwned(s) => SqlValue::Text(s.to_string()),
+        AnyValue::UInt8(u) => SqlValue::Integer(u as i64),
+        AnyValue::UInt16(u) => SqlValue::Integer(u as i64),
+        AnyValue::UInt32(u) => SqlValue::Integer(u as i64),
+        // sqlite integers are signed 64 bit, larger values are kept exactly as text
+        AnyValue::UInt64(u) => i64::try_from(u)
+            .map(SqlValue::Integer)
+            .unwrap_or_else(|_| SqlValue::Text(u.to_string())),
+        AnyValue::Int8(i) => SqlValue::Integer(i as i64),
+        AnyValue::Int16(i) => SqlValue::Integer(i as i64),
+        AnyValue::Int32(i) => SqlValue::Integer(i as i64),
+        AnyValue::Int64(i) => SqlValue::Integer(i),
+        AnyValue::Float32(f) => SqlValue::Real(f as f64),
+        AnyValue::Float64(f) => SqlValue::Real(f),
+        other => SqlValue::Text(other.to_string()),
+    }
+}
+
+fn insert_rows(tx: &Transaction, name: &str, df: &DataFrame) -> Result<()> {
+    let columns: Vec<String> = df
+        .get_column_names()
+        .iter()
+        .map(|c| quote_ident(c))
+        .collect();
+    let sql: String = format!(
+        "INSERT INTO {} ({}) VALUES ({})",
+        quote_ident(name),
+        columns.join(", "),
+        vec!["?"; columns.len()].join(", ")
+    );
+    let mut statement = tx.prepare(&sql).map_err(|e| NormError::export(name, e))?;
+    // convert column by column, then bind row by row
+    let cells: Vec<Vec<SqlValue>> = df
+        .get_columns()
+        .iter()
+        .map(|c| {
+            c.as_materialized_series()
+                .rechunk()
+                .iter()
+                .map(to_sql_value)
+                .collect()
+        })
+        .collect();
+    for row in 0..df.height() {
+        statement
+            .execute(params_from_iter(cells.iter().map(|c| &c[row])))
+            .map_err(|e| NormError::export(name, e))?;
+    }
+    Ok(())
+}
+
+impl DataBase {
+    pub fn write_sqlite(&self, path: impl AsRef<Path>) -> Result<()> {
+        // writes every table into a sqlite file with primary and foreign key constraints,
+        // list columns become child tables since sqlite has no array type
+        let flat: DataBase = self.for_dialect(Dialect::Sqlite)?;
+        let order: Vec<&str> = flat.relations().parent_first(flat.table_names());
+
+        let mut conn: Connection = Connection::open(path)?;
+        conn.pragma_update(None, "foreign_keys", true)?;
+        let tx: Transaction = conn.transaction()?;
+        for name in order.iter().rev() {
+            // replace the tables of a previous export, children first
+            tx.execute(&format!("DROP TABLE IF EXISTS {}", quote_ident(name)), [])
+                .map_err(|e| NormError::export(name, e))?;
+        }
+        for name in &order {
+            let schema: TableSchema = flat.table_schema(name)?;
+            tx.execute(&create_table(name, &schema, Dialect::Sqlite), [])
+                .map_err(|e| NormError::export(name, e))?;
+            for index in create_indexes(name, &schema) {
+                tx.execute(&index, [])
+                    .map_err(|e| NormError::export(name, e))?;
+            }
+            if let Some(df) = flat.get(name) {
+                insert_rows(&tx, name, df)?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
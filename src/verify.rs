@@ -0,0 +1,167 @@
+use crate::dtype::Dtype;
+use derive_more::Display;
+use serde_json::{Map, Value};
+
+/// A way in which a value did not survive normalization and reconstruction unchanged.
+#[derive(Debug, Clone, PartialEq, Display)]
+pub enum Loss {
+    // an empty array is stored as a null cell, which is left out of the rebuilt document
+    #[display("empty array was stored as null")]
+    EmptyArray,
+    // explicit nulls, including columns `clean_nulls` dropped, read back as missing keys
+    #[display("null value was dropped")]
+    NullDropped,
+    // a column (or list) with mixed types was coerced to strings by `stringify_collection`
+    #[display("{original} was stringified to {rebuilt}")]
+    Stringified { original: Value, rebuilt: Value },
+    // an integer in a column that also holds floats came back as a float
+    #[display("{original} was promoted to {rebuilt}")]
+    Promoted { original: Value, rebuilt: Value },
+    // the rebuilt object lists the same keys in a different order
+    #[display("keys {original:?} were reordered to {rebuilt:?}")]
+    KeyOrder {
+        original: Vec<String>,
+        rebuilt: Vec<String>,
+    },
+    #[display("{original} is missing")]
+    Missing { original: Value },
+    #[display("{rebuilt} was added")]
+    Added { rebuilt: Value },
+    #[display("{original} was changed to {rebuilt}")]
+    Changed { original: Value, rebuilt: Value },
+}
+
+/// One lossy transformation and the json path of the value it affected.
+#[derive(Debug, Clone, PartialEq, Display)]
+#[display("{path}: {loss}")]
+pub struct Difference {
+    pub path: String,
+    pub loss: Loss,
+}
+
+/// Every difference between a document and the document rebuilt from its tables.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VerifyReport {
+    pub(crate) differences: Vec<Difference>,
+}
+
+impl VerifyReport {
+    pub fn is_lossless(&self) -> bool {
+        self.differences.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.differences.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.differences.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Difference> {
+        self.differences.iter()
+    }
+
+    pub(crate) fn compare(original: &Value, rebuilt: &Value) -> Self {
+        let mut report: VerifyReport = VerifyReport::default();
+        report.diff("$".to_string(), original, Some(rebuilt));
+        report
+    }
+
+    fn push(&mut self, path: String, loss: Loss) {
+        self.differences.push(Difference { path, loss });
+    }
+
+    fn diff(&mut self, path: String, original: &Value, rebuilt: Option<&Value>) {
+        match (original, rebuilt) {
+            (Value::Array(a), None | Some(Value::Null)) if a.is_empty() => {
+                self.push(path, Loss::EmptyArray)
+            }
+            (Value::Null, None) => self.push(path, Loss::NullDropped),
+            (_, None) => self.push(
+                path,
+                Loss::Missing {
+                    original: original.clone(),
+                },
+            ),
+            (Value::Object(o), Some(Value::Object(r))) => self.diff_object(path, o, r),
+            (Value::Array(o), Some(Value::Array(r))) if o.len() == r.len() => {
+                for (i, (item, rebuilt_item)) in o.iter().zip(r).enumerate() {
+                    self.diff(format!("{}[{}]", path, i), item, Some(rebuilt_item));
+                }
+            }
+            (_, Some(r)) if original == r => {}
+            (_, Some(r)) => {
+                let loss: Loss = Self::classify(original, r);
+                self.push(path, loss)
+            }
+        }
+    }
+
+    fn diff_object(
+        &mut self,
+        path: String,
+        original: &Map<String, Value>,
+        rebuilt: &Map<String, Value>,
+    ) {
+        for (key, value) in original {
+            self.diff(format!("{}.{}", path, key), value, rebuilt.get(key));
+        }
+        for (key, value) in rebuilt {
+            if !original.contains_key(key) {
+                self.push(
+                    format!("{}.{}", path, key),
+                    Loss::Added {
+                        rebuilt: value.clone(),
+                    },
+                );
+            }
+        }
+        // only the keys both objects share can be out of order
+        let original_keys: Vec<String> = original
+            .keys()
+            .filter(|k| rebuilt.contains_key(*k))
+            .cloned()
+            .collect();
+        let rebuilt_keys: Vec<String> = rebuilt
+            .keys()
+            .filter(|k| original.contains_key(*k))
+            .cloned()
+            .collect();
+        if original_keys != rebuilt_keys {
+            self.push(
+                path,
+                Loss::KeyOrder {
+                    original: original_keys,
+                    rebuilt: rebuilt_keys,
+                },
+            );
+        }
+    }
+
+    fn classify(original: &Value, rebuilt: &Value) -> Loss {
+        let (original, rebuilt) = (original.clone(), rebuilt.clone());
+        // compare against the same string form `stringify_collection` produces
+        let stringified: Option<String> = Dtype::from_value(original.clone())
+            .ok()
+            .map(|d| d.to_string());
+        match &rebuilt {
+            Value::String(s) if !original.is_string() && stringified.as_ref() == Some(s) => {
+                Loss::Stringified { original, rebuilt }
+            }
+            Value::Number(n) if n.is_f64() && original.as_f64() == n.as_f64() => {
+                Loss::Promoted { original, rebuilt }
+            }
+            _ => Loss::Changed { original, rebuilt },
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a VerifyReport {
+    type Item = &'a Difference;
+    type IntoIter = std::slice::Iter<'a, Difference>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.differences.iter()
+    }
+}
@@ -9,3 +9,1357 @@
 //     let data: Normifier = Normifier::from_value(payload).expect("error parsing data from paylaod");
 //     println!("{:?}", data);
 // }
+
+use normify::{
+    Action, AncestorKeys, Cardinality, CopyFormat, CsvOptions, DataBase, Dialect, ForeignKey,
+    IdStrategy, ListHandling, Loss, NamingStrategy, NormError, Normifier, Relationship, Rules,
+    TableSchema, Value, VerifyReport, from_value,
+};
+use polars::prelude::{
+    DataFrame, DataType, IntoColumn, NamedFrom, ParquetReader, SerReader, Series, df,
+};
+use serde_json::json;
+
+fn normify(payload: Value) -> Normifier {
+    from_value(payload).expect("error parsing data from payload")
+}
+
+#[test]
+fn ragged_records_produce_aligned_columns() {
+    let payload: Value = json!([
+        {"a": 1, "b": "x"},
+        {"b": "y", "c": true},
+        {"a": 3}
+    ]);
+    let data: Normifier = normify(payload);
+    let (_, table) = data.iter_tables().next().unwrap();
+    assert_eq!(table.n_rows(), 3);
+    for (col_name, col) in table.iter_columns() {
+        assert_eq!(col.len(), 3, "column {} is not row-aligned", col_name);
+    }
+    let c: Vec<String> = table
+        .iter_columns()
+        .find(|(name, _)| name.as_str() == "c")
+        .map(|(_, col)| col.iter().map(|x| x.to_string()).collect())
+        .unwrap();
+    assert_eq!(c, vec!["null", "true", "null"]);
+}
+
+fn filing() -> Value {
+    json!({
+        "filing_uuid": "f-1",
+        "client": {"name": "Acme", "address": {"city": "Austin"}},
+        "registrant": {"name": "Lobby LLC", "address": {"city": "Dallas"}}
+    })
+}
+
+fn table_names(data: &Normifier) -> Vec<String> {
+    data.iter_tables().map(|(name, _)| name.clone()).collect()
+}
+
+#[test]
+fn path_qualified_names_keep_nested_objects_apart() {
+    let data: Normifier = normify(filing());
+    let names: Vec<String> = table_names(&data);
+    assert!(names.contains(&"root_client_address".to_string()));
+    assert!(names.contains(&"root_registrant_address".to_string()));
+    assert_eq!(
+        data.table_for_path("$.registrant.address"),
+        Some("root_registrant_address")
+    );
+}
+
+#[test]
+fn leaf_only_names_are_disambiguated_on_collision() {
+    let mut data: Normifier = Normifier::new().with_naming(NamingStrategy::LeafOnly);
+    data.process_value(filing(), "root_table").unwrap();
+    assert_eq!(
+        data.table_for_path("$.client.address"),
+        Some("address_table")
+    );
+    assert_eq!(
+        data.table_for_path("$.registrant.address"),
+        Some("registrant_address_table")
+    );
+}
+
+#[test]
+fn custom_names_are_recorded_per_path() {
+    let strategy = NamingStrategy::custom(|path| path.leaf().unwrap_or("x").to_uppercase());
+    let mut data: Normifier = Normifier::new().with_naming(strategy);
+    // a normifier with a custom strategy can still be moved to another thread
+    fn assert_send_sync<T: Send + Sync>(_: &T) {}
+    assert_send_sync(&data);
+    data.process_value(filing(), "root_table").unwrap();
+    assert_eq!(data.table_for_path("$.client.address"), Some("ADDRESS"));
+    assert_eq!(
+        data.table_for_path("$.registrant.address"),
+        Some("ADDRESS_2")
+    );
+    let paths: Vec<String> = data
+        .iter_table_paths()
+        .map(|(p, _)| p.to_string())
+        .collect();
+    assert_eq!(paths[0], "$");
+}
+
+#[test]
+fn primitive_root_is_an_error() {
+    let err = from_value(json!(42)).unwrap_err();
+    assert_eq!(err.path(), Some("$"));
+    assert!(matches!(err, NormError::InvalidRoot { .. }));
+}
+
+#[test]
+fn object_inside_primitive_array_reports_its_path() {
+    let err = from_value(json!({"client": {"tags": ["a", {"b": 1}]}})).unwrap_err();
+    assert_eq!(err.path(), Some("$.client.tags[1]"));
+}
+
+#[test]
+fn non_object_in_root_array_is_an_error() {
+    let err = from_value(json!([{"a": 1}, 2])).unwrap_err();
+    assert!(matches!(err, NormError::ExpectedObject { .. }));
+    assert_eq!(err.path(), Some("$[1]"));
+}
+
+#[test]
+fn to_database_builds_one_frame_per_table() {
+    let db: DataBase = normify::to_database(filing()).unwrap();
+    assert_eq!(db.tables.len(), 5);
+    let same: DataBase = normify::from_value(filing())
+        .unwrap()
+        .into_database()
+        .unwrap();
+    assert_eq!(same.tables.len(), db.tables.len());
+}
+
+#[test]
+fn database_tables_are_keyed_by_name() {
+    let mut db: DataBase = normify::to_database(filing()).unwrap();
+    let names: Vec<&str> = db.table_names().collect();
+    assert_eq!(
+        names,
+        vec![
+            "root_table",
+            "root_client",
+            "root_client_address",
+            "root_registrant",
+            "root_registrant_address"
+        ]
+    );
+    assert_eq!(db.get("root_client").unwrap().height(), 1);
+
+    db.rename("root_client", "clients").unwrap();
+    assert_eq!(db.table_names().nth(1), Some("clients"));
+    assert!(matches!(
+        db.rename("clients", "root_table"),
+        Err(NormError::DuplicateTable(_))
+    ));
+    assert!(matches!(
+        db.rename("missing", "other"),
+        Err(NormError::UnknownTable(_))
+    ));
+
+    assert!(db.remove("clients").is_some());
+    assert!(db.get("clients").is_none());
+    assert_eq!(db.len(), 4);
+    // nothing references the removed table any more
+    let address: &TableSchema = db.schema("root_client_address").unwrap();
+    assert!(address.foreign_key("root_client_id").is_none());
+    assert!(!db.to_ddl(Dialect::Sqlite).unwrap().contains("\"clients\""));
+}
+
+#[test]
+fn schemas_record_keys_coercions_and_nullability() {
+    let payload: Value = json!([
+        {"code": 1, "note": "a", "client": {"name": "Acme"}},
+        {"code": "B", "client": {"name": "Initech"}}
+    ]);
+    let db: DataBase = normify::to_database(payload).unwrap();
+
+    let root: &TableSchema = db.schema("root_table").unwrap();
+    assert_eq!(root.primary_key.as_deref(), Some("id"));
+    assert!(root.is_stringified("code"));
+    assert!(root.is_nullable("note"));
+    assert!(!root.is_nullable("id"));
+
+    let client: &TableSchema = db.schema("root_client").unwrap();
+    let fk: &ForeignKey = client.foreign_key("root_table_id").unwrap();
+    assert_eq!(fk.references, "root_table");
+    assert_eq!(fk.referenced_column, "id");
+    assert_eq!(client.dtype("name"), Some(&DataType::String));
+}
+
+#[test]
+fn relationships_describe_the_table_tree() {
+    let payload: Value = json!({
+        "filing_uuid": "f-1",
+        "client": {"name": "Acme"},
+        "lobbyists": [{"name": "Ann"}, {"name": "Bo", "covered_positions": [{"title": "x"}]}]
+    });
+    let data: Normifier = normify(payload);
+    let children: Vec<&Relationship> = data.relations().children_of("root_table").collect();
+    assert_eq!(children.len(), 2);
+    assert_eq!(children[0].child_table, "root_client");
+    assert_eq!(children[0].cardinality, Cardinality::OneToOne);
+    assert_eq!(children[1].json_key, "lobbyists");
+    assert_eq!(children[1].cardinality, Cardinality::OneToMany);
+    assert_eq!(children[1].fk_column, "root_table_id");
+
+    let mut db: DataBase = data.into_database().unwrap();
+    let grandchild: &Relationship = db
+        .relations()
+        .parent_of("root_lobbyists_covered_positions")
+        .unwrap();
+    assert_eq!(grandchild.parent_table, "root_lobbyists");
+
+    db.rename("root_lobbyists", "lobbyists").unwrap();
+    assert_eq!(db.relations().children_of("lobbyists").count(), 1);
+    db.remove("lobbyists");
+    assert_eq!(db.relations().len(), 1);
+}
+
+#[test]
+fn mixed_numeric_columns_are_promoted() {
+    let payload: Value = json!([
+        {"n": 1, "m": 1, "l": [1, 2], "big": 18446744073709551615u64},
+        {"n": -1, "m": 2.5, "l": [-3, 4.5], "big": -1},
+    ]);
+    let db: DataBase = normify::to_database(payload).unwrap();
+    let root: &TableSchema = db.schema("root_table").unwrap();
+    assert_eq!(root.dtype("n"), Some(&DataType::Int64));
+    assert_eq!(root.dtype("m"), Some(&DataType::Float64));
+    assert_eq!(
+        root.dtype("l"),
+        Some(&DataType::List(Box::new(DataType::Float64)))
+    );
+    // u64 values above i64::MAX cannot share a column with negative values
+    assert!(root.is_stringified("big"));
+    assert!(!root.is_stringified("n"));
+}
+
+#[test]
+fn parquet_export_writes_tables_and_manifest() {
+    let dir = tempfile::tempdir().unwrap();
+    let payload: Value = json!({
+        "filing_uuid": "f-1",
+        "tags": ["a", "b"],
+        "lobbyists": [{"name": "Ann"}, {"name": "Bo"}]
+    });
+    let db: DataBase = normify::to_database(payload).unwrap();
+    db.write_parquet(dir.path()).unwrap();
+
+    let file = std::fs::File::open(dir.path().join("root_table.parquet")).unwrap();
+    let root: DataFrame = ParquetReader::new(file).finish().unwrap();
+    assert!(root.equals_missing(db.get("root_table").unwrap()));
+    assert_eq!(
+        root.column("tags").unwrap().dtype(),
+        &DataType::List(Box::new(DataType::String))
+    );
+
+    let manifest: Value =
+        serde_json::from_str(&std::fs::read_to_string(dir.path().join("manifest.json")).unwrap())
+            .unwrap();
+    assert_eq!(manifest["tables"][1]["name"], "root_lobbyists");
+    assert_eq!(manifest["tables"][1]["file"], "root_lobbyists.parquet");
+    assert_eq!(manifest["tables"][1]["rows"], 2);
+    assert_eq!(manifest["tables"][1]["primary_key"], "id");
+    assert_eq!(
+        manifest["tables"][1]["foreign_keys"][0]["references"],
+        "root_table"
+    );
+    assert_eq!(manifest["relationships"][0]["cardinality"], "one_to_many");
+}
+
+#[test]
+fn ipc_export_round_trips_tables_schemas_and_relationships() {
+    let dir = tempfile::tempdir().unwrap();
+    let payload: Value = json!({
+        "filing_uuid": "f-1",
+        "tags": ["a", "b"],
+        "client": {"name": "Acme", "mixed": [1, "x"]},
+        "lobbyists": [{"name": "Ann", "age": 40}, {"name": "Bo"}]
+    });
+    let db: DataBase = normify::to_database(payload).unwrap();
+    db.write_ipc(dir.path()).unwrap();
+    assert!(dir.path().join("root_lobbyists.arrow").exists());
+
+    let loaded: DataBase = DataBase::read_ipc(dir.path()).unwrap();
+    assert!(loaded.table_names().eq(db.table_names()));
+    for (name, df) in db.iter() {
+        assert!(loaded.get(name).unwrap().equals_missing(df), "{}", name);
+        assert_eq!(loaded.schema(name), db.schema(name), "{}", name);
+    }
+    assert_eq!(loaded.relations(), db.relations());
+
+    std::fs::write(dir.path().join("manifest.json"), "{\"tables\": 1}").unwrap();
+    assert!(matches!(
+        DataBase::read_ipc(dir.path()),
+        Err(NormError::Manifest(_))
+    ));
+}
+
+#[test]
+fn to_json_rebuilds_the_nested_document() {
+    let payload: Value = json!([
+        {
+            "filing_uuid": "f-1",
+            "amount": 1.5,
+            "tags": ["a", "b"],
+            "client": {"name": "Acme", "address": {"city": "Austin"}},
+            "lobbyists": [{"name": "Ann", "codes": [1, 2]}, {"name": "Bo"}]
+        },
+        {"filing_uuid": "f-2", "lobbyists": [{"name": "Cy"}]}
+    ]);
+    let db: DataBase = normify::to_database(payload.clone()).unwrap();
+    assert_eq!(db.to_json().unwrap(), payload);
+    // list columns moved into child tables are folded back into arrays
+    assert_eq!(
+        db.explode_list_columns().unwrap().to_json().unwrap(),
+        payload
+    );
+
+    let object: Value = filing();
+    let db: DataBase = normify::to_database(object.clone()).unwrap();
+    assert_eq!(db.to_json().unwrap(), object);
+}
+
+#[test]
+fn verify_reports_every_lossy_transformation() {
+    let report: VerifyReport = normify::verify(json!([
+        {"name": "Ann", "mixed": 1, "n": 1, "tags": [], "gone": null},
+        {"name": "Bo", "mixed": "x", "n": 2.5, "extra": {"k": 1}, "tags": ["a"], "gone": null}
+    ]))
+    .unwrap();
+    let found: Vec<(&str, &Loss)> = report.iter().map(|d| (d.path.as_str(), &d.loss)).collect();
+    assert!(found.contains(&("$[0].tags", &Loss::EmptyArray)));
+    assert!(found.contains(&("$[0].gone", &Loss::NullDropped)));
+    assert!(found.contains(&(
+        "$[0].mixed",
+        &Loss::Stringified {
+            original: json!(1),
+            rebuilt: json!("1")
+        }
+    )));
+    assert!(found.contains(&(
+        "$[0].n",
+        &Loss::Promoted {
+            original: json!(1),
+            rebuilt: json!(1.0)
+        }
+    )));
+    // scalar columns come before child tables in the rebuilt object
+    assert!(
+        found
+            .iter()
+            .any(|(path, loss)| *path == "$[1]" && matches!(loss, Loss::KeyOrder { .. }))
+    );
+    assert!(!report.is_lossless());
+
+    let clean: VerifyReport = normify::verify(filing()).unwrap();
+    assert!(clean.is_lossless(), "{:?}", clean);
+}
+
+#[test]
+fn columns_follow_json_key_order() {
+    // keys keep their source order instead of being sorted, after the generated key columns
+    let db: DataBase =
+        normify::to_database(json!({"zeta": 1, "alpha": 2, "child": {"b": 1, "a": 2}})).unwrap();
+    assert_eq!(
+        db.get("root_table").unwrap().get_column_names(),
+        vec!["id", "zeta", "alpha"]
+    );
+    assert_eq!(
+        db.get("root_child").unwrap().get_column_names(),
+        vec!["id", "root_table_id", "b", "a"]
+    );
+}
+
+fn ids_with(strategy: IdStrategy, payload: Value) -> DataBase {
+    let mut norm: Normifier = Normifier::new().with_id_strategy(strategy);
+    norm.process_value(payload, "root_table").unwrap();
+    norm.into_database().unwrap()
+}
+
+#[test]
+fn id_strategies_are_deterministic_and_typed() {
+    let payload: Value = json!([
+        {"filing_uuid": "f-1", "lobbyists": [{"name": "Ann"}, {"name": "Bo"}]},
+        {"filing_uuid": "f-2", "lobbyists": [{"name": "Ann"}]}
+    ]);
+
+    let db: DataBase = ids_with(IdStrategy::Sequential, payload.clone());
+    let lobbyists: &DataFrame = db.get("root_lobbyists").unwrap();
+    assert!(
+        lobbyists
+            .column("id")
+            .unwrap()
+            .as_materialized_series()
+            .equals(&Series::new("id".into(), [1i64, 2, 3]))
+    );
+    assert!(
+        lobbyists
+            .column("root_table_id")
+            .unwrap()
+            .as_materialized_series()
+            .equals(&Series::new("root_table_id".into(), [1i64, 1, 2]))
+    );
+
+    // identical objects still get distinct hashes, and hashes are stable across runs
+    let first: DataBase = ids_with(IdStrategy::ContentHash, payload.clone());
+    let lobbyists: &DataFrame = first.get("root_lobbyists").unwrap();
+    assert_eq!(lobbyists.column("id").unwrap().n_unique().unwrap(), 3);
+    let again: DataBase = ids_with(IdStrategy::ContentHash, payload.clone());
+    assert!(
+        first
+            .get("root_lobbyists")
+            .unwrap()
+            .equals(again.get("root_lobbyists").unwrap())
+    );
+    let repeated: Value = json!([
+        {"lob": [{"n": "Ann", "pos": [{"t": 1}]}]},
+        {"lob": [{"n": "Ann", "pos": [{"t": 1}]}]}
+    ]);
+    let db: DataBase = ids_with(IdStrategy::ContentHash, repeated.clone());
+    let dir = tempfile::tempdir().unwrap();
+    db.write_sqlite(dir.path().join("hashes.db")).unwrap();
+    assert_eq!(db.to_json().unwrap(), repeated);
+
+    let first: DataBase = ids_with(IdStrategy::UuidV5, payload.clone());
+    let again: DataBase = ids_with(IdStrategy::UuidV5, payload.clone());
+    assert!(
+        first
+            .get("root_lobbyists")
+            .unwrap()
+            .equals(again.get("root_lobbyists").unwrap())
+    );
+
+    let db: DataBase = ids_with(IdStrategy::NaturalKey("filing_uuid".to_string()), payload);
+    let root: &DataFrame = db.get("root_table").unwrap();
+    assert_eq!(
+        root.column("id").unwrap().get(1).unwrap().str_value(),
+        "f-2"
+    );
+    assert_eq!(
+        db.get("root_lobbyists")
+            .unwrap()
+            .column("root_table_id")
+            .unwrap()
+            .get(2)
+            .unwrap()
+            .str_value(),
+        "f-2"
+    );
+
+    // a keyed table rejects missing and duplicate keys instead of mixing in fallback ids
+    let norm_with = |payload: Value| {
+        Normifier::new()
+            .with_id_strategy(IdStrategy::NaturalKey("code".to_string()))
+            .process_value(payload, "root_table")
+    };
+    assert!(matches!(
+        norm_with(json!([{"code": 2}, {"x": 1}])),
+        Err(NormError::Build { .. })
+    ));
+    assert!(matches!(
+        norm_with(json!([{"code": 2}, {"code": 2}])),
+        Err(NormError::Build { .. })
+    ));
+    assert!(matches!(
+        norm_with(json!([{"code": 2}, {"code": "2"}])),
+        Err(NormError::Build { .. })
+    ));
+    assert!(norm_with(json!([{"x": 1}, {"code": 1}])).is_ok());
+}
+
+#[test]
+fn source_id_fields_are_kept_next_to_generated_ids() {
+    let payload: Value = json!([
+        {"name": "Ann", "lobbyists": [{"name": "Cy"}]},
+        {"id": 7, "name": "Bo", "lobbyists": [{"id": "x", "name": "Di"}]}
+    ]);
+    let db: DataBase = normify::to_database(payload.clone()).unwrap();
+    let root: &TableSchema = db.schema("root_table").unwrap();
+    assert_eq!(root.primary_key.as_deref(), Some("_id"));
+    assert_eq!(root.dtype("id"), Some(&DataType::UInt64));
+    assert_eq!(
+        db.schema("root_lobbyists").unwrap().foreign_keys[0].referenced_column,
+        "_id"
+    );
+    assert_eq!(db.to_json().unwrap(), payload);
+
+    // the same holds for foreign keys, also when the source field appears after the first row
+    let payload: Value = json!([
+        {"a": {"n": 1}},
+        {"a": {"root_table_id": 5}}
+    ]);
+    let db: DataBase = normify::to_database(payload.clone()).unwrap();
+    let child: &TableSchema = db.schema("root_a").unwrap();
+    assert!(child.foreign_key("_root_table_id").is_some());
+    assert!(child.foreign_key("root_table_id").is_none());
+    let dir = tempfile::tempdir().unwrap();
+    db.write_sqlite(dir.path().join("fk.db")).unwrap();
+    assert_eq!(db.to_json().unwrap(), payload);
+}
+
+#[test]
+fn natural_keys_are_detected_and_promoted() {
+    let payload: Value = json!([
+        {"filing_uuid": "f-1", "name": "Same", "lobbyists": [{"name": "Ann"}]},
+        {"filing_uuid": "f-2", "name": "Same", "lobbyists": [{"name": "Bo"}]}
+    ]);
+    let db: DataBase = normify::to_database(payload.clone()).unwrap();
+    assert_eq!(
+        db.schema("root_table").unwrap().candidate_keys,
+        vec!["filing_uuid".to_string()]
+    );
+
+    let mut norm: Normifier = Normifier::new().with_natural_keys(true);
+    norm.process_value(payload.clone(), "root_table").unwrap();
+    let db: DataBase = norm.into_database().unwrap();
+    let root: &TableSchema = db.schema("root_table").unwrap();
+    assert_eq!(root.primary_key.as_deref(), Some("filing_uuid"));
+    assert!(root.dtype("id").is_none());
+    let lobbyists: &DataFrame = db.get("root_lobbyists").unwrap();
+    assert_eq!(
+        lobbyists
+            .column("root_table_id")
+            .unwrap()
+            .get(1)
+            .unwrap()
+            .str_value(),
+        "f-2"
+    );
+    assert_eq!(
+        db.schema("root_lobbyists").unwrap().foreign_keys[0].referenced_column,
+        "filing_uuid"
+    );
+    // `name` is unique in the child table but does not look like an identifier
+    assert_eq!(
+        db.schema("root_lobbyists").unwrap().primary_key.as_deref(),
+        Some("id")
+    );
+    assert_eq!(db.to_json().unwrap(), payload);
+}
+
+#[test]
+fn dedup_stores_repeated_objects_once() {
+    let payload: Value = json!([
+        {
+            "filing_uuid": "f-1",
+            "registrant": {"name": "Lobby LLC", "address": {"city": "Dallas"}},
+            "entities": [{"name": "House"}, {"name": "Senate"}]
+        },
+        {
+            "filing_uuid": "f-2",
+            "registrant": {"name": "Lobby LLC", "address": {"city": "Dallas"}},
+            "entities": [{"name": "Senate"}]
+        }
+    ]);
+    let mut norm: Normifier = Normifier::new().with_dedup(true);
+    norm.process_value(payload.clone(), "root_table").unwrap();
+    let db: DataBase = norm.into_database().unwrap();
+
+    assert_eq!(db.get("root_registrant").unwrap().height(), 1);
+    assert_eq!(db.get("root_registrant_address").unwrap().height(), 1);
+    assert_eq!(db.get("root_entities").unwrap().height(), 2);
+    assert_eq!(db.get("root_entities_link").unwrap().height(), 3);
+    assert_eq!(
+        db.schema("root_table")
+            .unwrap()
+            .foreign_key("root_registrant_id"),
+        Some(&ForeignKey {
+            column: "root_registrant_id".to_string(),
+            references: "root_registrant".to_string(),
+            referenced_column: "id".to_string(),
+        })
+    );
+    let cardinalities: Vec<Cardinality> = db.relations().iter().map(|r| r.cardinality).collect();
+    assert!(cardinalities.contains(&Cardinality::ManyToOne));
+    assert!(cardinalities.contains(&Cardinality::ManyToMany));
+
+    // shared tables are created before the tables referencing them
+    let ddl: String = db.to_ddl(Dialect::Postgres).unwrap();
+    let position = |table: &str| ddl.find(&format!("CREATE TABLE \"{}\"", table)).unwrap();
+    assert!(position("root_registrant") < position("root_table"));
+    assert!(position("root_entities") < position("root_entities_link"));
+    assert!(position("root_table") < position("root_entities_link"));
+
+    let dir = tempfile::tempdir().unwrap();
+    db.write_sqlite(dir.path().join("dedup.db")).unwrap();
+    assert_eq!(db.to_json().unwrap(), payload);
+}
+
+#[test]
+fn junction_tables_link_shared_array_items() {
+    let payload: Value = json!([
+        {"filing_uuid": "f-1", "lobbyists": [{"name": "Ann"}, {"name": "Bo"}], "notes": [{"text": "a"}]},
+        {"filing_uuid": "f-2", "lobbyists": [{"name": "Bo"}], "notes": [{"text": "b"}]}
+    ]);
+
+    // `Bo` is listed by both filings, the distinct notes stay one-to-many
+    let mut norm: Normifier = Normifier::new().with_junction_detection(true);
+    norm.process_value(payload.clone(), "root_table").unwrap();
+    let db: DataBase = norm.into_database().unwrap();
+    let lobbyists: &DataFrame = db.get("root_lobbyists").unwrap();
+    assert_eq!(lobbyists.height(), 2);
+    assert!(lobbyists.column("root_table_id").is_err());
+    let link: &DataFrame = db.get("root_lobbyists_link").unwrap();
+    assert_eq!(
+        link.get_column_names(),
+        vec!["root_table_id", "root_lobbyists_id", "_ord"]
+    );
+    assert!(
+        link.column("_ord")
+            .unwrap()
+            .as_materialized_series()
+            .equals(&Series::new("_ord".into(), [0u64, 1, 0]))
+    );
+    assert!(
+        db.get("root_notes")
+            .unwrap()
+            .column("root_table_id")
+            .is_ok()
+    );
+    assert!(db.get("root_notes_link").is_none());
+    assert_eq!(db.to_json().unwrap(), payload);
+
+    // a configured path gets a junction table even without repeated items
+    let mut norm: Normifier = Normifier::new().with_junction("$.notes");
+    norm.process_value(payload.clone(), "root_table").unwrap();
+    let db: DataBase = norm.into_database().unwrap();
+    assert_eq!(db.get("root_notes_link").unwrap().height(), 2);
+    assert!(db.get("root_lobbyists_link").is_none());
+    assert_eq!(db.to_json().unwrap(), payload);
+
+    // detection sees every line of newline delimited json, later values are rejected
+    let lines: &str = "{\"l\": [{\"k\": 1}]}\n{\"l\": [{\"k\": 1}]}\n";
+    let mut norm: Normifier = Normifier::new().with_junction_detection(true);
+    norm.process_json_lines(lines, "root_table", "l.jsonl")
+        .unwrap();
+    assert!(matches!(
+        norm.process_value(json!({"l": []}), "root_table"),
+        Err(NormError::JunctionDetection)
+    ));
+    let db: DataBase = norm.into_database().unwrap();
+    assert_eq!(db.get("root_l").unwrap().height(), 1);
+    assert_eq!(db.get("root_l_link").unwrap().height(), 2);
+    assert_eq!(
+        db.to_json().unwrap(),
+        json!([{"l": [{"k": 1}]}, {"l": [{"k": 1}]}])
+    );
+
+    // a junction table never shares its name with the table of another path
+    let payload: Value = json!({"tags": [{"a": 1}], "tags_link": [{"b": 2}]});
+    let mut norm: Normifier = Normifier::new().with_junction("$.tags");
+    norm.process_value(payload.clone(), "root_table").unwrap();
+    let db: DataBase = norm.into_database().unwrap();
+    assert_eq!(
+        db.get("root_tags_link").unwrap().get_column_names(),
+        vec!["root_table_id", "root_tags_id", "_ord"]
+    );
+    assert_eq!(
+        db.get("root_tags_link_2").unwrap().get_column_names(),
+        vec!["id", "root_table_id", "b"]
+    );
+    assert_eq!(db.to_json().unwrap(), payload);
+}
+
+fn tagged() -> DataBase {
+    normify::to_database(json!([
+        {"name": "Ann", "tags": ["a", "b"]},
+        {"name": "Bo", "tags": ["c"]}
+    ]))
+    .unwrap()
+}
+
+fn read_csv(path: std::path::PathBuf) -> Vec<String> {
+    std::fs::read_to_string(path)
+        .unwrap()
+        .lines()
+        .map(|l| l.split(',').skip(1).collect::<Vec<&str>>().join(","))
+        .collect()
+}
+
+#[test]
+fn csv_export_encodes_lists_as_json_or_joined_text() {
+    let db: DataBase = tagged();
+    let dir = tempfile::tempdir().unwrap();
+    db.write_csv(dir.path(), &CsvOptions::default()).unwrap();
+    let rows: Vec<String> = read_csv(dir.path().join("root_table.csv"));
+    assert_eq!(rows[0], "name,tags");
+    assert_eq!(rows[1], r#"Ann,"[""a"",""b""]""#);
+
+    let dir = tempfile::tempdir().unwrap();
+    let options: CsvOptions = CsvOptions::default().with_lists(ListHandling::Join("|".into()));
+    db.write_csv(dir.path(), &options).unwrap();
+    let rows: Vec<String> = read_csv(dir.path().join("root_table.csv"));
+    assert_eq!(rows[1..], ["Ann,a|b", "Bo,c"]);
+
+    // joined floats keep every digit
+    let db: DataBase = normify::to_database(json!({"x": [0.12345678912345, 1e20]})).unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    db.write_csv(dir.path(), &options).unwrap();
+    let rows: Vec<String> = read_csv(dir.path().join("root_table.csv"));
+    assert_eq!(rows[1], "0.12345678912345|100000000000000000000");
+}
+
+#[test]
+fn csv_export_can_explode_lists_into_child_tables() {
+    let db: DataBase = tagged();
+    let dir = tempfile::tempdir().unwrap();
+    let options: CsvOptions = CsvOptions::default().with_lists(ListHandling::Explode);
+    db.write_csv(dir.path(), &options).unwrap();
+    assert_eq!(read_csv(dir.path().join("root_table.csv"))[0], "name");
+    let rows: Vec<String> = read_csv(dir.path().join("root_table_tags.csv"));
+    assert_eq!(rows, ["_ord,tags", "0,a", "1,b", "0,c"]);
+
+    let exploded: DataBase = db.explode_list_columns().unwrap();
+    let link: &Relationship = exploded.relations().parent_of("root_table_tags").unwrap();
+    assert_eq!(link.fk_column, "root_table_id");
+    let ids = |table: &str, column: &str| -> Vec<String> {
+        exploded.get(table).unwrap()[column]
+            .str()
+            .unwrap()
+            .into_iter()
+            .map(|x| x.unwrap().to_string())
+            .collect()
+    };
+    let parents: Vec<String> = ids("root_table", "id");
+    assert_eq!(
+        ids("root_table_tags", "root_table_id"),
+        [parents[0].clone(), parents[0].clone(), parents[1].clone()]
+    );
+
+    // an exploded column never replaces a table of the same name
+    let db: DataBase =
+        normify::to_database(json!({"tags": [1, 2], "table_tags": {"x": 1}})).unwrap();
+    let exploded: DataBase = db.explode_list_columns().unwrap();
+    assert_eq!(
+        exploded.get("root_table_tags").unwrap().get_column_names(),
+        db.get("root_table_tags").unwrap().get_column_names()
+    );
+    assert_eq!(exploded.get("root_table_tags_2").unwrap().height(), 2);
+    assert_eq!(
+        exploded
+            .relations()
+            .parent_of("root_table_tags_2")
+            .unwrap()
+            .json_key,
+        "tags"
+    );
+}
+
+#[test]
+fn sqlite_export_declares_keys_and_inserts_rows() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("filings.db");
+    let payload: Value = json!([
+        {"filing_uuid": "f-1", "amount": 10.5, "tags": ["x"], "lobbyists": [{"name": "Ann"}, {"name": "Bo"}]},
+        {"filing_uuid": "f-2", "amount": 3, "tags": ["y", "z"], "lobbyists": [{"name": "Cy"}]}
+    ]);
+    let db: DataBase = normify::to_database(payload).unwrap();
+    db.write_sqlite(&path).unwrap();
+    // writing twice replaces the previous export
+    db.write_sqlite(&path).unwrap();
+
+    let conn = rusqlite::Connection::open(&path).unwrap();
+    let count = |table: &str| -> i64 {
+        conn.query_row(&format!("SELECT COUNT(*) FROM \"{}\"", table), [], |r| {
+            r.get(0)
+        })
+        .unwrap()
+    };
+    assert_eq!(count("root_table"), 2);
+    assert_eq!(count("root_lobbyists"), 3);
+    assert_eq!(count("root_table_tags"), 3);
+
+    let pk: i64 = conn
+        .query_row(
+            "SELECT pk FROM pragma_table_info('root_table') WHERE name = 'id'",
+            [],
+            |r| r.get(0),
+        )
+        .unwrap();
+    assert_eq!(pk, 1);
+    let (fk_table, fk_from): (String, String) = conn
+        .query_row(
+            "SELECT \"table\", \"from\" FROM pragma_foreign_key_list('root_lobbyists')",
+            [],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )
+        .unwrap();
+    assert_eq!(
+        (fk_table.as_str(), fk_from.as_str()),
+        ("root_table", "root_table_id")
+    );
+    let amount_type: String = conn
+        .query_row(
+            "SELECT type FROM pragma_table_info('root_table') WHERE name = 'amount'",
+            [],
+            |r| r.get(0),
+        )
+        .unwrap();
+    assert_eq!(amount_type, "REAL");
+    let violations: i64 = conn
+        .query_row("SELECT COUNT(*) FROM pragma_foreign_key_check", [], |r| {
+            r.get(0)
+        })
+        .unwrap();
+    assert_eq!(violations, 0);
+}
+
+fn ddl_fixture() -> DataBase {
+    normify::to_database(json!({
+        "name": "Acme",
+        "tags": ["a"],
+        "lobbyists": [{"name": "Ann", "age": 40}, {"name": "Bo"}]
+    }))
+    .unwrap()
+}
+
+#[test]
+fn postgres_ddl_uses_arrays_keys_and_indexes() {
+    let ddl: String = ddl_fixture().to_ddl(Dialect::Postgres).unwrap();
+    let expected: &str = r#"CREATE TABLE "root_table" (
+    "id" TEXT PRIMARY KEY,
+    "name" TEXT NOT NULL,
+    "tags" TEXT[] NOT NULL
+);
+
+CREATE TABLE "root_lobbyists" (
+    "id" TEXT PRIMARY KEY,
+    "root_table_id" TEXT NOT NULL,
+    "name" TEXT NOT NULL,
+    "age" BIGINT,
+    FOREIGN KEY ("root_table_id") REFERENCES "root_table" ("id")
+);
+
+CREATE INDEX "idx_root_lobbyists_root_table_id" ON "root_lobbyists" ("root_table_id");
+"#;
+    assert_eq!(ddl, expected);
+
+    // only values beyond the i64 range need the wider numeric type
+    let big: DataBase = normify::to_database(json!({"n": 18446744073709551615u64})).unwrap();
+    assert!(
+        big.to_ddl(Dialect::Postgres)
+            .unwrap()
+            .contains("\"n\" NUMERIC(20, 0)")
+    );
+}
+
+#[test]
+fn sqlite_and_duckdb_ddl_differ_in_list_handling() {
+    let sqlite: String = ddl_fixture().to_ddl(Dialect::Sqlite).unwrap();
+    assert!(sqlite.contains("CREATE TABLE \"root_table_tags\""));
+    assert!(!sqlite.contains("[]"));
+    assert!(sqlite.contains("\"age\" INTEGER,"));
+
+    let duckdb: String = ddl_fixture().to_ddl(Dialect::DuckDb).unwrap();
+    assert!(duckdb.contains("\"tags\" VARCHAR[] NOT NULL"));
+    assert!(duckdb.contains("\"age\" UBIGINT,"));
+}
+
+fn copy_fixture() -> DataBase {
+    let mut db: DataBase = DataBase::new();
+    let df: DataFrame = df!(
+        "id" => ["a", "b"],
+        "note" => [Some("tab\there\nnew \\ line"), None],
+        "ok" => [true, false],
+    )
+    .unwrap();
+    let tags: Series = Series::new(
+        "tags".into(),
+        [
+            Series::new("".into(), [Some("x,y"), Some("say \"hi\"")]),
+            Series::new("".into(), [None::<&str>]),
+        ],
+    );
+    db.insert("root_table", df.hstack(&[tags.into_column()]).unwrap());
+    db
+}
+
+#[test]
+fn pg_copy_text_format_escapes_fields_and_arrays() {
+    let db: DataBase = copy_fixture();
+    assert_eq!(
+        db.copy_statement("root_table", CopyFormat::Text).unwrap(),
+        r#"COPY "root_table" ("id", "note", "ok", "tags") FROM STDIN;"#
+    );
+    let expected: &str = concat!(
+        "a\ttab\\there\\nnew \\\\ line\tt\t{\"x,y\",\"say \\\\\"hi\\\\\"\"}\n",
+        "b\t\\N\tf\t{NULL}\n",
+    );
+    assert_eq!(
+        db.copy_data("root_table", CopyFormat::Text).unwrap(),
+        expected
+    );
+}
+
+#[test]
+fn pg_copy_csv_format_quotes_fields() {
+    let db: DataBase = copy_fixture();
+    let expected: &str = concat!(
+        "\"a\",\"tab\there\nnew \\ line\",\"t\",\"{\"\"x,y\"\",\"\"say \\\"\"hi\\\"\"\"\"}\"\n",
+        "\"b\",,\"f\",\"{NULL}\"\n",
+    );
+    assert_eq!(
+        db.copy_data("root_table", CopyFormat::Csv).unwrap(),
+        expected
+    );
+
+    // floats keep every digit, in fields and in arrays
+    let mut db: DataBase = DataBase::new();
+    let df: DataFrame = df!("x" => [0.12345678912345, 123456789.123]).unwrap();
+    let list: Series = Series::new(
+        "l".into(),
+        [
+            Series::new("".into(), [1e20, 0.5]),
+            Series::new("".into(), [2.5f64]),
+        ],
+    );
+    db.insert("root_table", df.hstack(&[list.into_column()]).unwrap());
+    assert_eq!(
+        db.copy_data("root_table", CopyFormat::Text).unwrap(),
+        "0.12345678912345\t{100000000000000000000,0.5}\n123456789.123\t{2.5}\n"
+    );
+}
+
+#[test]
+fn pg_copy_load_script_orders_tables_parent_first() {
+    let mut db: DataBase = ddl_fixture();
+    // move the parent behind its child to check the script does not rely on creation order
+    db.tables.move_index(0, 1);
+    assert_eq!(db.table_names().last(), Some("root_table"));
+    let dir = tempfile::tempdir().unwrap();
+    db.write_pg_copy(dir.path(), CopyFormat::Text).unwrap();
+    let script: String = std::fs::read_to_string(dir.path().join("load.sql")).unwrap();
+    let lines: Vec<&str> = script.lines().collect();
+    assert_eq!(lines[2], "\\ir schema.sql");
+    assert!(
+        lines[3].starts_with(
+            "\\copy \"root_table\" (\"id\", \"name\", \"tags\") FROM 'root_table.dat'"
+        )
+    );
+    assert!(lines[4].starts_with("\\copy \"root_lobbyists\""));
+    assert_eq!(lines[5], "COMMIT;");
+    assert!(dir.path().join("root_lobbyists.dat").exists());
+    assert!(dir.path().join("schema.sql").exists());
+}
+
+#[test]
+fn ordinals_record_array_positions() {
+    let payload: Value = json!([
+        {"name": "a", "items": [{"sku": "x"}, {"sku": "y"}, {"sku": "z"}]},
+        {"name": "b", "items": [{"sku": "w"}], "tags": ["t1", "t2"]}
+    ]);
+    let mut norm: Normifier = Normifier::new().with_ordinals(true);
+    norm.process_value(payload.clone(), "root_table").unwrap();
+    let mut db: DataBase = norm.into_database().unwrap();
+
+    // the root table is not a child of any array, so it has no ordinal
+    assert!(db.get("root_table").unwrap().column("_ord").is_err());
+    let items: &DataFrame = db.get("root_items").unwrap();
+    assert!(
+        items
+            .column("_ord")
+            .unwrap()
+            .as_materialized_series()
+            .equals(&Series::new("_ord".into(), [0u64, 1, 2, 0]))
+    );
+    let exploded: DataBase = db.explode_list_columns().unwrap();
+    assert!(
+        exploded
+            .get("root_table_tags")
+            .unwrap()
+            .column("_ord")
+            .is_ok()
+    );
+
+    // array order survives rows being reordered
+    let reversed: DataFrame = db.get("root_items").unwrap().reverse();
+    db.insert("root_items", reversed);
+    assert_eq!(db.to_json().unwrap(), payload);
+}
+
+#[test]
+fn provenance_records_pointer_and_source() {
+    let payload: Value = json!({
+        "results": [
+            {"id": 1, "lobbying_activities": [{"code": "TAX"}, {"code": "a/b"}]}
+        ]
+    });
+    let mut norm: Normifier = Normifier::new().with_provenance(true);
+    norm.process_value(payload.clone(), "root_table").unwrap();
+    let db: DataBase = norm.into_database().unwrap();
+    let pointers = |table: &str| -> Vec<String> {
+        db.get(table)
+            .unwrap()
+            .column("_pointer")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_no_null_iter()
+            .map(str::to_string)
+            .collect()
+    };
+    assert_eq!(pointers("root_table"), vec![""]);
+    assert_eq!(pointers("root_results"), vec!["/results/0"]);
+    assert_eq!(
+        pointers("root_results_lobbying_activities"),
+        vec![
+            "/results/0/lobbying_activities/0",
+            "/results/0/lobbying_activities/1"
+        ]
+    );
+    assert_eq!(db.to_json().unwrap(), payload);
+
+    // each line of newline delimited json is a record of its source file
+    let lines: &str = "{\"name\": \"a\"}\n\n{\"name\": \"b\", \"tags\": [{\"t\": 1}]}\n";
+    let mut norm: Normifier = Normifier::new().with_provenance(true);
+    norm.process_json_lines(lines, "root_table", "filings.jsonl")
+        .unwrap();
+    let db: DataBase = norm.into_database().unwrap();
+    let tags: &DataFrame = db.get("root_tags").unwrap();
+    assert_eq!(
+        tags.column("_source").unwrap().str().unwrap().get(0),
+        Some("filings.jsonl")
+    );
+    assert!(
+        db.get("root_table")
+            .unwrap()
+            .column("_record")
+            .unwrap()
+            .as_materialized_series()
+            .equals(&Series::new("_record".into(), [1u64, 3]))
+    );
+    assert_eq!(
+        db.to_json().unwrap(),
+        json!([{"name": "a"}, {"name": "b", "tags": [{"t": 1}]}])
+    );
+
+    // separately processed values are rebuilt as an array of records
+    let mut norm: Normifier = Normifier::new().with_provenance(true);
+    norm.process_document(json!({"name": "a"}), "root_table", "a.json", 1)
+        .unwrap();
+    norm.process_value(json!({"name": "b"}), "root_table")
+        .unwrap();
+    let db: DataBase = norm.into_database().unwrap();
+    assert_eq!(db.to_json().unwrap(), json!([{"name": "a"}, {"name": "b"}]));
+
+    // a failed document does not shift the pointers of the next one
+    let mut norm: Normifier = Normifier::new().with_provenance(true);
+    assert!(
+        norm.process_value(json!({"a": {"b": [1, {"c": 2}]}}), "root_table")
+            .is_err()
+    );
+    norm.process_value(json!({"x": {"y": 1}}), "root_table")
+        .unwrap();
+    let db: DataBase = norm.into_database().unwrap();
+    let pointer = |table: &str| -> Option<String> {
+        let df: &DataFrame = db.get(table).unwrap();
+        let pointers = df.column("_pointer").unwrap().str().unwrap();
+        pointers.get(df.height() - 1).map(str::to_string)
+    };
+    assert_eq!(pointer("root_table").as_deref(), Some(""));
+    assert_eq!(pointer("root_x").as_deref(), Some("/x"));
+}
+
+#[test]
+fn metadata_columns_keep_json_keys_with_the_same_name() {
+    let payload: Value = json!({
+        "items": [{"_ord": "first", "_pointer": "p"}, {"_ord": "second", "_pointer": "q"}]
+    });
+    let mut norm: Normifier = Normifier::new().with_ordinals(true).with_provenance(true);
+    norm.process_value(payload.clone(), "root_table").unwrap();
+    let mut db: DataBase = norm.into_database().unwrap();
+
+    let items: &DataFrame = db.get("root_items").unwrap();
+    assert_eq!(
+        items.column("_ord").unwrap().str().unwrap().get(1),
+        Some("second")
+    );
+    assert!(
+        items
+            .column("__ord")
+            .unwrap()
+            .as_materialized_series()
+            .equals(&Series::new("__ord".into(), [0u64, 1]))
+    );
+    assert_eq!(
+        items.column("__pointer").unwrap().str().unwrap().get(0),
+        Some("/items/0")
+    );
+    let schema: &TableSchema = db.schema("root_items").unwrap();
+    assert_eq!(schema.ordinal.as_deref(), Some("__ord"));
+    assert!(schema.is_metadata("__pointer") && !schema.is_metadata("_pointer"));
+
+    let reversed: DataFrame = db.get("root_items").unwrap().reverse();
+    db.insert("root_items", reversed);
+    assert_eq!(db.to_json().unwrap(), payload);
+}
+
+#[test]
+fn ancestor_keys_reference_the_root_record() {
+    let payload: Value = json!([
+        {"filing_uuid": "f-1", "activities": [{"code": "TAX", "lobbyists": [{"name": "Ann", "covered_position": {"title": "aide"}}]}]}
+    ]);
+    let mut norm: Normifier = Normifier::new().with_ancestor_keys(AncestorKeys::Root);
+    norm.process_value(payload.clone(), "root_table").unwrap();
+    let db: DataBase = norm.into_database().unwrap();
+    let lobbyists: &DataFrame = db.get("root_activities_lobbyists").unwrap();
+    assert_eq!(
+        lobbyists.get_column_names(),
+        vec!["id", "root_table_id", "root_activities_id", "name"]
+    );
+    assert_eq!(
+        lobbyists.column("root_table_id").unwrap().get(0).unwrap(),
+        db.get("root_table")
+            .unwrap()
+            .column("id")
+            .unwrap()
+            .get(0)
+            .unwrap()
+    );
+    assert_eq!(
+        db.schema("root_activities_lobbyists_covered_position")
+            .unwrap()
+            .foreign_key("root_table_id")
+            .map(|fk| fk.references.as_str()),
+        Some("root_table")
+    );
+    // the direct parent is not duplicated
+    assert_eq!(
+        db.get("root_activities").unwrap().get_column_names(),
+        vec!["id", "root_table_id", "code"]
+    );
+    assert_eq!(db.to_json().unwrap(), payload);
+
+    let mut norm: Normifier = Normifier::new().with_ancestor_keys(AncestorKeys::All);
+    norm.process_value(payload.clone(), "root_table").unwrap();
+    let db: DataBase = norm.into_database().unwrap();
+    assert_eq!(
+        db.get("root_activities_lobbyists_covered_position")
+            .unwrap()
+            .get_column_names(),
+        vec![
+            "id",
+            "root_table_id",
+            "root_activities_id",
+            "root_activities_lobbyists_id",
+            "title"
+        ]
+    );
+    assert_eq!(db.to_json().unwrap(), payload);
+
+    // junction rows reference the root record as well
+    let mut norm: Normifier = Normifier::new()
+        .with_ancestor_keys(AncestorKeys::Root)
+        .with_junction("$.activities.lobbyists");
+    norm.process_value(payload.clone(), "root_table").unwrap();
+    let db: DataBase = norm.into_database().unwrap();
+    assert_eq!(
+        db.get("root_activities_lobbyists_link")
+            .unwrap()
+            .get_column_names(),
+        vec![
+            "root_activities_id",
+            "root_activities_lobbyists_id",
+            "root_table_id",
+            "_ord"
+        ]
+    );
+    assert_eq!(
+        db.schema("root_activities_lobbyists_link")
+            .unwrap()
+            .foreign_key("root_table_id")
+            .map(|fk| fk.references.as_str()),
+        Some("root_table")
+    );
+    assert_eq!(db.to_json().unwrap(), payload);
+}
+
+#[test]
+fn flattening_inlines_one_to_one_objects() {
+    let payload: Value = json!([
+        {
+            "name": "Acme",
+            "address": {"city": "Austin", "geo": {"lat": 30.2}, "phones": [{"n": "555"}]},
+            "contact": {"email": "a@acme.test"}
+        }
+    ]);
+    let mut norm: Normifier = Normifier::new().with_flatten_depth(1);
+    norm.process_value(payload.clone(), "root_table").unwrap();
+    let db: DataBase = norm.into_database().unwrap();
+    assert_eq!(
+        db.get("root_table").unwrap().get_column_names(),
+        vec!["id", "name", "address_city", "contact_email"]
+    );
+    // deeper objects keep their own table, arrays always do
+    assert!(db.get("root_address_geo").is_some());
+    assert_eq!(
+        db.get("root_address_phones").unwrap().get_column_names(),
+        vec!["id", "root_table_id", "n"]
+    );
+    assert!(db.get("root_address").is_none());
+    assert!(db.get("root_contact").is_none());
+
+    let mut norm: Normifier = Normifier::new().with_flatten("$.address.geo");
+    norm.process_value(payload, "root_table").unwrap();
+    let db: DataBase = norm.into_database().unwrap();
+    assert_eq!(
+        db.get("root_address").unwrap().get_column_names(),
+        vec!["id", "root_table_id", "city", "geo_lat"]
+    );
+    assert!(db.get("root_contact").is_some());
+
+    // json keys keep their names when a prefixed column would collide with them, in whichever
+    // row the collision first shows up
+    let payload: Value = json!([{"a": {"b": 2}}, {"a_b": 1, "a": {"b": 3}}]);
+    let mut norm: Normifier = Normifier::new().with_flatten_depth(1);
+    norm.process_value(payload.clone(), "root_table").unwrap();
+    let db: DataBase = norm.into_database().unwrap();
+    let root: &DataFrame = db.get("root_table").unwrap();
+    assert_eq!(root.get_column_names(), vec!["id", "_a_b", "a_b"]);
+    assert!(
+        root.column("_a_b")
+            .unwrap()
+            .as_materialized_series()
+            .equals(&Series::new("_a_b".into(), [2u64, 3]))
+    );
+    assert!(
+        root.column("a_b")
+            .unwrap()
+            .as_materialized_series()
+            .equals_missing(&Series::new("a_b".into(), [None, Some(1u64)]))
+    );
+}
+
+#[test]
+fn rules_configure_normalization_per_path() {
+    let payload: Value = json!([
+        {
+            "filing_uuid": "f-1",
+            "address": {"city": "Austin"},
+            "raw": {"source": "senate", "page": 3},
+            "tags": ["tax", "trade"],
+            "debug": {"trace": "x"},
+            "lobbyists": [{"lobbyist_id": 7, "name": "Ann", "contact": {"phone": "555"}}]
+        }
+    ]);
+    let toml_rules: &str = r#"
+        [rules."$.address"]
+        action = "flatten"
+
+        [rules."$.raw"]
+        action = "json"
+
+        [rules."$.tags"]
+        action = "explode"
+
+        [rules."$.debug"]
+        action = "drop"
+
+        [rules."$.lobbyists"]
+        rename = "lobbyists"
+        primary_key = "lobbyist_id"
+    "#;
+    let rules: Rules = Rules::from_toml(toml_rules).unwrap();
+    let json_rules: Value = json!({
+        "rules": {
+            "$.address": {"action": "flatten"},
+            "$.raw": {"action": "json"},
+            "$.tags": {"action": "explode"},
+            "$.debug": {"action": "drop"},
+            "$.lobbyists": {"rename": "lobbyists", "primary_key": "lobbyist_id"}
+        }
+    });
+    assert_eq!(Rules::from_json(&json_rules.to_string()).unwrap(), rules);
+    assert_eq!(
+        rules.get("$.tags").and_then(|r| r.action),
+        Some(Action::Explode)
+    );
+
+    let mut norm: Normifier = Normifier::new().with_rules(rules);
+    norm.process_value(payload, "root_table").unwrap();
+    let db: DataBase = norm.into_database().unwrap();
+    let root: &DataFrame = db.get("root_table").unwrap();
+    assert_eq!(
+        root.get_column_names(),
+        vec!["id", "filing_uuid", "address_city", "raw"]
+    );
+    assert_eq!(
+        root.column("raw").unwrap().str().unwrap().get(0),
+        Some(r#"{"source":"senate","page":3}"#)
+    );
+    assert_eq!(
+        db.get("root_tags").unwrap().get_column_names(),
+        vec!["root_table_id", "_ord", "tags"]
+    );
+    assert!(db.get("root_debug").is_none());
+    assert_eq!(
+        db.schema("lobbyists").unwrap().primary_key.as_deref(),
+        Some("lobbyist_id")
+    );
+    assert_eq!(
+        db.schema("root_lobbyists_contact")
+            .unwrap()
+            .foreign_key("lobbyists_id")
+            .map(|fk| fk.referenced_column.as_str()),
+        Some("lobbyist_id")
+    );
+
+    assert!(matches!(
+        Rules::from_json(r#"{"rules": {"$.x": {"action": "shred"}}}"#),
+        Err(NormError::Rules(_))
+    ));
+
+    // a renamed table keeps its name, other paths are named around it
+    let payload: Value = json!({"b": {"x": 1}, "a": {"y": 2}});
+    let rules: Rules = Rules::from_json(r#"{"rules": {"$.a": {"rename": "root_b"}}}"#).unwrap();
+    let mut norm: Normifier = Normifier::new().with_rules(rules);
+    norm.process_value(payload.clone(), "root_table").unwrap();
+    let names: Vec<String> = norm.iter_table_paths().map(|(_, n)| n.clone()).collect();
+    assert_eq!(names.iter().filter(|n| *n == "root_b").count(), 1);
+    let db: DataBase = norm.into_database().unwrap();
+    assert!(db.get("root_b").unwrap().column("y").is_ok());
+    assert_eq!(db.to_json().unwrap(), payload);
+
+    // a rename to a name another path already holds is rejected
+    let rules: Rules = Rules::from_json(r#"{"rules": {"$.a": {"rename": "root_table"}}}"#).unwrap();
+    let mut norm: Normifier = Normifier::new().with_rules(rules);
+    assert!(matches!(
+        norm.process_value(payload, "root_table"),
+        Err(NormError::Rules(_))
+    ));
+
+    // exploded tables get ancestor keys and keep a value named like their parent key
+    let payload: Value = json!({"a": {"tags": [1, 2], "root_a_id": [3]}});
+    let rules: Rules = Rules::from_json(
+        r#"{"rules": {"$.a.tags": {"action": "explode"}, "$.a.root_a_id": {"action": "explode"}}}"#,
+    )
+    .unwrap();
+    let mut norm: Normifier = Normifier::new()
+        .with_rules(rules)
+        .with_ancestor_keys(AncestorKeys::All);
+    norm.process_value(payload.clone(), "root_table").unwrap();
+    let db: DataBase = norm.into_database().unwrap();
+    assert_eq!(
+        db.get("root_a_tags").unwrap().get_column_names(),
+        vec!["root_a_id", "root_table_id", "_ord", "tags"]
+    );
+    let ids: &DataFrame = db.get("root_a_root_a_id").unwrap();
+    assert_eq!(
+        ids.get_column_names(),
+        vec!["_root_a_id", "root_table_id", "_ord", "root_a_id"]
+    );
+    assert_eq!(
+        db.relations()
+            .parent_of("root_a_root_a_id")
+            .unwrap()
+            .fk_column,
+        "_root_a_id"
+    );
+    assert_eq!(db.to_json().unwrap(), payload);
+}